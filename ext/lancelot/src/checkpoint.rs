@@ -0,0 +1,155 @@
+use arrow::ipc::reader::FileReader;
+use arrow::ipc::writer::FileWriter;
+use arrow_array::RecordBatch;
+use arrow_schema::ArrowError;
+use lance::dataset::{BatchInfo, UDFCheckpointStore};
+use lance_table::format::Fragment;
+use serde::{Deserialize, Serialize};
+use std::fs::{self, File};
+use std::path::PathBuf;
+
+/// What a backfill's checkpoint was computed for. A checkpoint left by a
+/// backfill with a different manifest is discarded rather than resumed.
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+pub struct CheckpointManifest {
+    pub version: u64,
+    pub column: String,
+    pub data_type: String,
+    pub read_columns: Option<Vec<String>>,
+    pub batch_size: Option<u32>,
+}
+
+/// Checkpoint store for column backfills, so an interrupted backfill can
+/// resume without calling the Ruby block again for finished batches.
+///
+/// Computed batches are kept as Arrow IPC files and finished fragments as
+/// JSON, one file each, in a directory used by a single backfill. The
+/// directory may be one the caller already uses, so only files this store
+/// writes are ever deleted from it.
+pub struct FileCheckpointStore {
+    dir: PathBuf,
+}
+
+impl FileCheckpointStore {
+    /// Open the checkpoint in `dir`, starting over if it was written for a
+    /// different dataset version, column, type, read columns or batch size
+    pub fn new(dir: impl Into<PathBuf>, manifest: &CheckpointManifest) -> std::io::Result<Self> {
+        let dir = dir.into();
+        let manifest_path = dir.join("manifest.json");
+
+        let existing = fs::read_to_string(&manifest_path).ok()
+            .and_then(|json| serde_json::from_str::<CheckpointManifest>(&json).ok());
+        let store = Self { dir };
+        if existing.as_ref() != Some(manifest) {
+            store.clear()?;
+            fs::create_dir_all(&store.dir)?;
+            fs::write(&manifest_path, serde_json::to_string(manifest)?)?;
+        }
+
+        Ok(store)
+    }
+
+    /// Delete the checkpoint, and its directory if nothing else is in it
+    pub fn remove(&self) -> std::io::Result<()> {
+        self.clear()?;
+        // Fails when the directory holds other files, which are left alone
+        let _ = fs::remove_dir(&self.dir);
+        Ok(())
+    }
+
+    /// Delete the files this store wrote, leaving anything else in the directory
+    fn clear(&self) -> std::io::Result<()> {
+        if !self.dir.exists() {
+            return Ok(());
+        }
+
+        for entry in fs::read_dir(&self.dir)? {
+            let entry = entry?;
+            let name = entry.file_name();
+            if entry.file_type()?.is_file() && is_checkpoint_file(&name.to_string_lossy()) {
+                fs::remove_file(entry.path())?;
+            }
+        }
+
+        Ok(())
+    }
+
+    fn batch_path(&self, info: &BatchInfo) -> PathBuf {
+        self.dir.join(format!("fragment-{}-batch-{}.arrow", info.fragment_id, info.batch_index))
+    }
+
+    fn fragment_path(&self, fragment_id: u32) -> PathBuf {
+        self.dir.join(format!("fragment-{}.json", fragment_id))
+    }
+}
+
+/// `manifest.json`, `fragment-<id>.json`, `fragment-<id>-batch-<index>.arrow`,
+/// or a temporary file left while writing one of them
+fn is_checkpoint_file(name: &str) -> bool {
+    if name == "manifest.json" {
+        return true;
+    }
+
+    let name = name.strip_suffix(".tmp").unwrap_or(name);
+    let Some(rest) = name.strip_prefix("fragment-") else {
+        return false;
+    };
+    let all_digits = |s: &str| !s.is_empty() && s.bytes().all(|b| b.is_ascii_digit());
+
+    if let Some(id) = rest.strip_suffix(".json") {
+        return all_digits(id);
+    }
+    match rest.strip_suffix(".arrow").and_then(|rest| rest.split_once("-batch-")) {
+        Some((id, index)) => all_digits(id) && all_digits(index),
+        None => false,
+    }
+}
+
+fn to_lance_error(e: impl std::fmt::Display) -> lance::Error {
+    ArrowError::ExternalError(e.to_string().into()).into()
+}
+
+impl UDFCheckpointStore for FileCheckpointStore {
+    fn get_batch(&self, info: &BatchInfo) -> lance::Result<Option<RecordBatch>> {
+        let path = self.batch_path(info);
+        if !path.exists() {
+            return Ok(None);
+        }
+
+        let file = File::open(path).map_err(to_lance_error)?;
+        let mut reader = FileReader::try_new(file, None)?;
+        reader.next().transpose().map_err(Into::into)
+    }
+
+    fn insert_batch(&self, info: BatchInfo, batch: RecordBatch) -> lance::Result<()> {
+        // Write to a temporary file first so a crash never leaves a partial batch behind
+        let path = self.batch_path(&info);
+        let tmp_path = path.with_extension("arrow.tmp");
+
+        let file = File::create(&tmp_path).map_err(to_lance_error)?;
+        let mut writer = FileWriter::try_new(file, &batch.schema())?;
+        writer.write(&batch)?;
+        writer.finish()?;
+
+        fs::rename(tmp_path, path).map_err(to_lance_error)
+    }
+
+    fn get_fragment(&self, fragment_id: u32) -> lance::Result<Option<Fragment>> {
+        let path = self.fragment_path(fragment_id);
+        if !path.exists() {
+            return Ok(None);
+        }
+
+        let json = fs::read_to_string(path).map_err(to_lance_error)?;
+        serde_json::from_str(&json).map(Some).map_err(to_lance_error)
+    }
+
+    fn insert_fragment(&self, fragment: Fragment) -> lance::Result<()> {
+        let path = self.fragment_path(fragment.id as u32);
+        let tmp_path = path.with_extension("json.tmp");
+
+        let json = serde_json::to_string(&fragment).map_err(to_lance_error)?;
+        fs::write(&tmp_path, json).map_err(to_lance_error)?;
+        fs::rename(tmp_path, path).map_err(to_lance_error)
+    }
+}
@@ -0,0 +1,173 @@
+use chrono::{DateTime, Duration, Utc};
+use futures::stream::TryStreamExt;
+use lance::Dataset;
+use lance_table::format::{DeletionFileType, Manifest};
+use lance_table::io::commit::{ManifestLocation, ManifestNamingScheme};
+use lance_table::io::manifest::{read_manifest, read_manifest_indexes};
+use object_store::path::Path;
+use object_store::ObjectMeta;
+use std::collections::{HashMap, HashSet};
+
+/// Lance leaves files that no version references alone for this long, since
+/// they may belong to a commit that is still in progress
+const UNVERIFIED_THRESHOLD_DAYS: i64 = 7;
+
+/// Versions and files that `cleanup_old_versions` would remove
+pub struct CleanupPlan {
+    pub versions: Vec<u64>,
+    pub tagged_versions: Vec<u64>,
+    pub files: Vec<(String, u64)>,
+}
+
+/// Work out what a cleanup would remove without deleting anything, using the
+/// same rules as Lance's `cleanup_old_versions`.
+///
+/// A version is removable when it is older than the cutoff, is not the latest
+/// version and is not tagged; removable tagged versions are reported so the
+/// caller can raise as Lance would. A file is removable when it is referenced
+/// only by removable versions, or when no version references it and it is
+/// older than a week or `delete_unverified` is set. Paths are relative to the
+/// dataset root.
+pub async fn plan_cleanup(
+    dataset: &Dataset,
+    cutoff: DateTime<Utc>,
+    tagged: &HashSet<u64>,
+    delete_unverified: bool,
+) -> lance::Result<CleanupPlan> {
+    let object_store = dataset.object_store();
+
+    // List the dataset once and read each manifest directly, as Lance's cleanup does
+    let objects = list_objects(dataset).await?;
+
+    let latest = dataset.latest_version_id().await?;
+    let mut kept_files = HashSet::new();
+    let mut removed_files = HashSet::new();
+    let mut versions = Vec::new();
+    let mut tagged_versions = Vec::new();
+
+    for (relative, meta) in &objects {
+        let Some(filename) = relative.strip_prefix("_versions/") else {
+            continue;
+        };
+        let Some(naming_scheme) = ManifestNamingScheme::detect_scheme(filename) else {
+            continue;
+        };
+        let Some(version) = naming_scheme.parse_version(filename) else {
+            continue;
+        };
+
+        let location = ManifestLocation {
+            version,
+            path: meta.location.clone(),
+            size: Some(meta.size),
+            naming_scheme,
+            e_tag: meta.e_tag.clone(),
+        };
+        let manifest = read_manifest(object_store, &location.path, location.size).await?;
+        let mut files = referenced_files(&manifest);
+        files.push(relative.clone());
+        for index in read_manifest_indexes(object_store, &location, &manifest).await? {
+            files.push(format!("_indices/{}", index.uuid));
+        }
+
+        let old = version != latest && manifest.timestamp() < cutoff;
+        if old && tagged.contains(&version) {
+            tagged_versions.push(version);
+        }
+        if old && !tagged.contains(&version) {
+            versions.push(version);
+            removed_files.extend(files);
+        } else {
+            kept_files.extend(files);
+        }
+    }
+
+    // Anything newer than this that no version references may be a commit in progress
+    let unverified_cutoff = Utc::now() - Duration::days(UNVERIFIED_THRESHOLD_DAYS);
+
+    let mut files: Vec<(String, u64)> = objects.iter()
+        .filter(|(relative, meta)| {
+            let Some(owner) = owning_file(relative) else {
+                return false;
+            };
+            if kept_files.contains(owner) {
+                return false;
+            }
+            removed_files.contains(owner) || delete_unverified || meta.last_modified < unverified_cutoff
+        })
+        .map(|(relative, meta)| (relative.clone(), meta.size))
+        .collect();
+    files.sort();
+    versions.sort_unstable();
+    tagged_versions.sort_unstable();
+
+    Ok(CleanupPlan { versions, tagged_versions, files })
+}
+
+/// Every file in the dataset directory and its size, by path relative to the
+/// dataset root
+pub async fn list_files(dataset: &Dataset) -> lance::Result<HashMap<String, u64>> {
+    Ok(list_objects(dataset).await?
+        .into_iter()
+        .map(|(relative, meta)| (relative, meta.size))
+        .collect())
+}
+
+async fn list_objects(dataset: &Dataset) -> lance::Result<HashMap<String, ObjectMeta>> {
+    // The current manifest lives at <base>/_versions/<file>
+    let manifest_parts: Vec<_> = dataset.manifest_location().path.parts().collect();
+    let base: Path = manifest_parts[..manifest_parts.len().saturating_sub(2)].iter().cloned().collect();
+
+    let objects: Vec<ObjectMeta> = dataset.object_store().read_dir_all(&base, None).try_collect().await?;
+    Ok(objects.into_iter()
+        .filter_map(|meta| {
+            let relative: Vec<String> = meta.location.prefix_match(&base)?
+                .map(|part| part.as_ref().to_string())
+                .collect();
+            Some((relative.join("/"), meta))
+        })
+        .collect())
+}
+
+/// The manifest, data, deletion, transaction or index entry a file belongs
+/// to, for files cleanup may remove. Index files belong to their index
+/// directory; everything else (tags, `_latest.manifest`) is never removed.
+fn owning_file(relative: &str) -> Option<&str> {
+    let (dir, rest) = relative.split_once('/')?;
+    match dir {
+        "_indices" => {
+            let uuid_len = rest.find('/').unwrap_or(rest.len());
+            Some(&relative[..dir.len() + 1 + uuid_len])
+        }
+        "_versions" if rest.ends_with(".manifest") => Some(relative),
+        "data" | "_deletions" | "_transactions" if !rest.contains('/') => Some(relative),
+        _ => None,
+    }
+}
+
+/// Every file a version's manifest points at: its transaction, data and deletion files
+fn referenced_files(manifest: &Manifest) -> Vec<String> {
+    let mut files = Vec::new();
+
+    if let Some(transaction_file) = &manifest.transaction_file {
+        files.push(format!("_transactions/{}", transaction_file));
+    }
+
+    for fragment in manifest.fragments.iter() {
+        for data_file in &fragment.files {
+            files.push(format!("data/{}", data_file.path));
+        }
+        if let Some(deletion_file) = &fragment.deletion_file {
+            let suffix = match deletion_file.file_type {
+                DeletionFileType::Array => "arrow",
+                DeletionFileType::Bitmap => "bin",
+            };
+            files.push(format!(
+                "_deletions/{}-{}-{}.{}",
+                fragment.id, deletion_file.read_version, deletion_file.id, suffix
+            ));
+        }
+    }
+
+    files
+}
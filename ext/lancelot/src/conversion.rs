@@ -1,18 +1,33 @@
-use magnus::{Error, Ruby, RHash, RArray, Symbol, Value, TryConvert, value::ReprValue};
+use magnus::{Error, Ruby, RHash, RArray, RClass, Module, Symbol, Value, TryConvert, value::ReprValue};
 use arrow_schema::{DataType, Schema as ArrowSchema};
-use arrow_array::{RecordBatch, StringArray, Float32Array, ArrayRef, Array, FixedSizeListArray};
+use arrow_array::{RecordBatch, StringArray, Float16Array, Float32Array, UInt8Array, ArrayRef, Array, FixedSizeListArray, Date32Array, ListArray};
+use arrow_array::builder::{ListBuilder, StringBuilder};
+use arrow_array::types::{Int32Type, Int64Type};
+use arrow::datatypes::f16;
 use std::collections::HashMap;
 use std::sync::Arc;
 
+/// Julian day number of 1970-01-01, the epoch of Arrow's Date32
+pub const UNIX_EPOCH_JULIAN_DAY: i64 = 2_440_588;
+
+/// A Ruby list value, with its elements converted for the list's element type
+enum ListValues {
+    Strings(Vec<Option<String>>),
+    Ints(Vec<Option<i64>>),
+}
+
 pub fn build_record_batch(
     data: RArray,
     schema: &ArrowSchema,
 ) -> Result<RecordBatch, Error> {
     let mut columns: HashMap<String, Vec<Option<String>>> = HashMap::new();
     let mut float_columns: HashMap<String, Vec<Option<f32>>> = HashMap::new();
+    let mut double_columns: HashMap<String, Vec<Option<f64>>> = HashMap::new();
     let mut int_columns: HashMap<String, Vec<Option<i64>>> = HashMap::new();
     let mut bool_columns: HashMap<String, Vec<Option<bool>>> = HashMap::new();
     let mut vector_columns: HashMap<String, Vec<Option<Vec<f32>>>> = HashMap::new();
+    let mut date_columns: HashMap<String, Vec<Option<i32>>> = HashMap::new();
+    let mut list_columns: HashMap<String, Vec<Option<ListValues>>> = HashMap::new();
     
     for field in schema.fields() {
         match field.data_type() {
@@ -22,7 +37,10 @@ pub fn build_record_batch(
             DataType::Float32 => {
                 float_columns.insert(field.name().to_string(), Vec::new());
             }
-            DataType::Int64 => {
+            DataType::Float64 => {
+                double_columns.insert(field.name().to_string(), Vec::new());
+            }
+            DataType::Int32 | DataType::Int64 => {
                 int_columns.insert(field.name().to_string(), Vec::new());
             }
             DataType::Boolean => {
@@ -31,6 +49,12 @@ pub fn build_record_batch(
             DataType::FixedSizeList(_, _) => {
                 vector_columns.insert(field.name().to_string(), Vec::new());
             }
+            DataType::Date32 => {
+                date_columns.insert(field.name().to_string(), Vec::new());
+            }
+            DataType::List(_) => {
+                list_columns.insert(field.name().to_string(), Vec::new());
+            }
             _ => {}
         }
     }
@@ -64,7 +88,15 @@ pub fn build_record_batch(
                         float_columns.get_mut(field.name()).unwrap().push(Some(f as f32));
                     }
                 }
-                DataType::Int64 => {
+                DataType::Float64 => {
+                    if value.is_nil() {
+                        double_columns.get_mut(field.name()).unwrap().push(None);
+                    } else {
+                        let f = f64::try_convert(value)?;
+                        double_columns.get_mut(field.name()).unwrap().push(Some(f));
+                    }
+                }
+                DataType::Int32 | DataType::Int64 => {
                     if value.is_nil() {
                         int_columns.get_mut(field.name()).unwrap().push(None);
                     } else {
@@ -91,6 +123,36 @@ pub fn build_record_batch(
                         vector_columns.get_mut(field.name()).unwrap().push(Some(vec));
                     }
                 }
+                DataType::Date32 => {
+                    if value.is_nil() {
+                        date_columns.get_mut(field.name()).unwrap().push(None);
+                    } else {
+                        // Accepts Date, DateTime or anything else that responds to #jd
+                        let julian_day: i64 = value.funcall("jd", ())?;
+                        let days = i32::try_from(julian_day - UNIX_EPOCH_JULIAN_DAY)
+                            .map_err(|_| Error::new(
+                                magnus::exception::range_error(),
+                                format!("Date out of range for column '{}'", field.name())
+                            ))?;
+                        date_columns.get_mut(field.name()).unwrap().push(Some(days));
+                    }
+                }
+                DataType::List(inner_field) => {
+                    if value.is_nil() {
+                        list_columns.get_mut(field.name()).unwrap().push(None);
+                    } else {
+                        let arr = RArray::try_convert(value)?;
+                        let values = match inner_field.data_type() {
+                            DataType::Utf8 => ListValues::Strings(arr.into_iter()
+                                .map(|v| if v.is_nil() { Ok(None) } else { String::try_convert(v).map(Some) })
+                                .collect::<Result<Vec<_>, _>>()?),
+                            _ => ListValues::Ints(arr.into_iter()
+                                .map(|v| if v.is_nil() { Ok(None) } else { i64::try_convert(v).map(Some) })
+                                .collect::<Result<Vec<_>, _>>()?),
+                        };
+                        list_columns.get_mut(field.name()).unwrap().push(Some(values));
+                    }
+                }
                 _ => {}
             }
         }
@@ -108,6 +170,21 @@ pub fn build_record_batch(
                 let values = float_columns.get(field.name()).unwrap();
                 Arc::new(Float32Array::from(values.clone()))
             }
+            DataType::Float64 => {
+                let values = double_columns.get(field.name()).unwrap();
+                Arc::new(arrow_array::Float64Array::from(values.clone()))
+            }
+            DataType::Int32 => {
+                let values = int_columns.get(field.name()).unwrap();
+                let values = values.iter()
+                    .map(|v| v.map(i32::try_from).transpose())
+                    .collect::<Result<Vec<_>, _>>()
+                    .map_err(|_| Error::new(
+                        magnus::exception::range_error(),
+                        format!("Value out of range for int32 column '{}'", field.name())
+                    ))?;
+                Arc::new(arrow_array::Int32Array::from(values))
+            }
             DataType::Int64 => {
                 let values = int_columns.get(field.name()).unwrap();
                 Arc::new(arrow_array::Int64Array::from(values.clone()))
@@ -138,14 +215,41 @@ pub fn build_record_batch(
                     }
                 }
                 
-                let flat_array = Float32Array::from(flat_values);
+                let flat_array: ArrayRef = match inner_field.data_type() {
+                    DataType::Float16 => Arc::new(Float16Array::from(
+                        flat_values.into_iter().map(f16::from_f32).collect::<Vec<_>>()
+                    )),
+                    DataType::UInt8 => {
+                        // Binary vectors hold packed bits, one byte per element
+                        let bytes = flat_values.into_iter()
+                            .map(|v| if (0.0..=255.0).contains(&v) && v.fract() == 0.0 {
+                                Ok(v as u8)
+                            } else {
+                                Err(Error::new(
+                                    magnus::exception::range_error(),
+                                    format!("Value {} out of range for uint8 vector '{}'", v, field.name())
+                                ))
+                            })
+                            .collect::<Result<Vec<_>, _>>()?;
+                        Arc::new(UInt8Array::from(bytes))
+                    }
+                    _ => Arc::new(Float32Array::from(flat_values)),
+                };
                 Arc::new(FixedSizeListArray::new(
                     inner_field.clone(),
                     *list_size,
-                    Arc::new(flat_array),
+                    flat_array,
                     None
                 ))
             }
+            DataType::Date32 => {
+                let values = date_columns.get(field.name()).unwrap();
+                Arc::new(Date32Array::from(values.clone()))
+            }
+            DataType::List(inner_field) => {
+                let values = list_columns.remove(field.name()).unwrap();
+                build_list_array(field.name(), inner_field.data_type(), values)?
+            }
             _ => return Err(Error::new(
                 magnus::exception::runtime_error(),
                 format!("Unsupported data type: {:?}", field.data_type())
@@ -159,12 +263,86 @@ pub fn build_record_batch(
         .map_err(|e| Error::new(magnus::exception::runtime_error(), e.to_string()))
 }
 
+fn build_list_array(name: &str, element_type: &DataType, values: Vec<Option<ListValues>>) -> Result<ArrayRef, Error> {
+    let out_of_range = || Error::new(
+        magnus::exception::range_error(),
+        format!("Value out of range for int32 list column '{}'", name)
+    );
+
+    let array: ListArray = match element_type {
+        DataType::Utf8 => {
+            let mut builder = ListBuilder::new(StringBuilder::new());
+            for list in values {
+                match list {
+                    Some(ListValues::Strings(items)) => {
+                        for item in items {
+                            builder.values().append_option(item);
+                        }
+                        builder.append(true);
+                    }
+                    _ => builder.append_null(),
+                }
+            }
+            builder.finish()
+        }
+        DataType::Int32 => {
+            let lists = values.into_iter()
+                .map(|list| match list {
+                    Some(ListValues::Ints(items)) => items.into_iter()
+                        .map(|item| item.map(i32::try_from).transpose().map_err(|_| out_of_range()))
+                        .collect::<Result<Vec<_>, _>>()
+                        .map(Some),
+                    _ => Ok(None),
+                })
+                .collect::<Result<Vec<_>, _>>()?;
+            ListArray::from_iter_primitive::<Int32Type, _, _>(lists)
+        }
+        _ => {
+            let lists = values.into_iter().map(|list| match list {
+                Some(ListValues::Ints(items)) => Some(items),
+                _ => None,
+            });
+            ListArray::from_iter_primitive::<Int64Type, _, _>(lists)
+        }
+    };
+
+    Ok(Arc::new(array))
+}
+
+/// Convert a list element array to a Ruby array, mapping nulls to nil
+fn list_to_ruby(ruby: &Ruby, values: &ArrayRef) -> Result<RArray, Error> {
+    let ruby_array = ruby.ary_new();
+
+    if let Some(strings) = values.as_any().downcast_ref::<StringArray>() {
+        for item in strings.iter() {
+            ruby_array.push(item)?;
+        }
+    } else if let Some(ints) = values.as_any().downcast_ref::<arrow_array::Int32Array>() {
+        for item in ints.iter() {
+            ruby_array.push(item)?;
+        }
+    } else if let Some(ints) = values.as_any().downcast_ref::<arrow_array::Int64Array>() {
+        for item in ints.iter() {
+            ruby_array.push(item)?;
+        }
+    }
+
+    Ok(ruby_array)
+}
+
 pub fn convert_batch_to_ruby(batch: &RecordBatch) -> Result<RArray, Error> {
     let ruby = Ruby::get().unwrap();
     let documents = ruby.ary_new();
     
     let num_rows = batch.num_rows();
     let schema = batch.schema();
+
+    // Dates come back as Ruby Date objects, so look the class up once per batch
+    let date_class: Option<RClass> = if schema.fields().iter().any(|f| f.data_type() == &DataType::Date32) {
+        Some(ruby.class_object().const_get("Date")?)
+    } else {
+        None
+    };
     
     for row_idx in 0..num_rows {
         let doc = ruby.hash_new();
@@ -203,6 +381,26 @@ pub fn convert_batch_to_ruby(batch: &RecordBatch) -> Result<RArray, Error> {
                         doc.aset(key, array.value(row_idx))?;
                     }
                 }
+                DataType::Float64 => {
+                    let array = column.as_any().downcast_ref::<arrow_array::Float64Array>()
+                        .ok_or_else(|| Error::new(magnus::exception::runtime_error(), "Failed to cast to Float64Array"))?;
+                    
+                    if array.is_null(row_idx) {
+                        doc.aset(key, ruby.qnil())?;
+                    } else {
+                        doc.aset(key, array.value(row_idx))?;
+                    }
+                }
+                DataType::Int32 => {
+                    let array = column.as_any().downcast_ref::<arrow_array::Int32Array>()
+                        .ok_or_else(|| Error::new(magnus::exception::runtime_error(), "Failed to cast to Int32Array"))?;
+                    
+                    if array.is_null(row_idx) {
+                        doc.aset(key, ruby.qnil())?;
+                    } else {
+                        doc.aset(key, array.value(row_idx))?;
+                    }
+                }
                 DataType::Int64 => {
                     let array = column.as_any().downcast_ref::<arrow_array::Int64Array>()
                         .ok_or_else(|| Error::new(magnus::exception::runtime_error(), "Failed to cast to Int64Array"))?;
@@ -213,6 +411,17 @@ pub fn convert_batch_to_ruby(batch: &RecordBatch) -> Result<RArray, Error> {
                         doc.aset(key, array.value(row_idx))?;
                     }
                 }
+                DataType::UInt64 => {
+                    // Row ids
+                    let array = column.as_any().downcast_ref::<arrow_array::UInt64Array>()
+                        .ok_or_else(|| Error::new(magnus::exception::runtime_error(), "Failed to cast to UInt64Array"))?;
+                    
+                    if array.is_null(row_idx) {
+                        doc.aset(key, ruby.qnil())?;
+                    } else {
+                        doc.aset(key, array.value(row_idx))?;
+                    }
+                }
                 DataType::Boolean => {
                     let array = column.as_any().downcast_ref::<arrow_array::BooleanArray>()
                         .ok_or_else(|| Error::new(magnus::exception::runtime_error(), "Failed to cast to BooleanArray"))?;
@@ -231,26 +440,58 @@ pub fn convert_batch_to_ruby(batch: &RecordBatch) -> Result<RArray, Error> {
                         doc.aset(key, ruby.qnil())?;
                     } else {
                         let values = array.value(row_idx);
-                        let float_array = values.as_any().downcast_ref::<Float32Array>()
-                            .ok_or_else(|| Error::new(magnus::exception::runtime_error(), "Failed to cast vector values to Float32Array"))?;
                         
-                        // CRITICAL: Verify the float_array has the expected size
+                        // CRITICAL: Verify the vector has the expected size
                         let expected_size = *list_size as usize;
-                        if float_array.len() != expected_size {
+                        if values.len() != expected_size {
                             return Err(Error::new(
                                 magnus::exception::runtime_error(),
                                 format!("Vector data corruption: expected {} elements but found {} for field '{}'",
-                                        expected_size, float_array.len(), field.name())
+                                        expected_size, values.len(), field.name())
                             ));
                         }
                         
                         let ruby_array = ruby.ary_new();
-                        for i in 0..expected_size {
-                            ruby_array.push(float_array.value(i))?;
+                        if let Some(half_array) = values.as_any().downcast_ref::<Float16Array>() {
+                            for value in half_array.values().iter() {
+                                ruby_array.push(value.to_f32())?;
+                            }
+                        } else if let Some(byte_array) = values.as_any().downcast_ref::<UInt8Array>() {
+                            for value in byte_array.values().iter() {
+                                ruby_array.push(*value)?;
+                            }
+                        } else {
+                            let float_array = values.as_any().downcast_ref::<Float32Array>()
+                                .ok_or_else(|| Error::new(magnus::exception::runtime_error(), "Failed to cast vector values to Float32Array"))?;
+                            for value in float_array.values().iter() {
+                                ruby_array.push(*value)?;
+                            }
                         }
                         doc.aset(key, ruby_array)?;
                     }
                 }
+                DataType::Date32 => {
+                    let array = column.as_any().downcast_ref::<Date32Array>()
+                        .ok_or_else(|| Error::new(magnus::exception::runtime_error(), "Failed to cast to Date32Array"))?;
+                    
+                    if array.is_null(row_idx) {
+                        doc.aset(key, ruby.qnil())?;
+                    } else if let Some(date_class) = date_class {
+                        let julian_day = array.value(row_idx) as i64 + UNIX_EPOCH_JULIAN_DAY;
+                        let date: Value = date_class.funcall("jd", (julian_day,))?;
+                        doc.aset(key, date)?;
+                    }
+                }
+                DataType::List(_) => {
+                    let array = column.as_any().downcast_ref::<ListArray>()
+                        .ok_or_else(|| Error::new(magnus::exception::runtime_error(), "Failed to cast to ListArray"))?;
+                    
+                    if array.is_null(row_idx) {
+                        doc.aset(key, ruby.qnil())?;
+                    } else {
+                        doc.aset(key, list_to_ruby(&ruby, &array.value(row_idx))?)?;
+                    }
+                }
                 _ => {
                     // Skip unsupported types for now
                 }
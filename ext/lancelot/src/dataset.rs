@@ -1,17 +1,36 @@
-use magnus::{Error, Ruby, RHash, RArray, Symbol, TryConvert, Value, function, method, RClass, Module, Object};
-use std::cell::RefCell;
+use magnus::{Error, Ruby, RHash, RArray, RString, Symbol, TryConvert, Value, function, method, RClass, Module, Object, r_hash::ForEach, block::Proc, value::{Opaque, ReprValue}};
+use std::cell::{Cell, Ref, RefCell, RefMut};
 use std::sync::Arc;
 use tokio::runtime::Runtime;
 use lance::Dataset;
-use lance::index::vector::VectorIndexParams;
+use lance::dataset::{BatchUDF, ColumnAlteration, NewColumnTransform, ProjectionRequest, UDFCheckpointStore};
+use lance::dataset::optimize::compact_files;
+use lance::dataset::transaction::{Operation, Transaction};
+use lance_table::format::Index;
 use lance_index::{IndexType, DatasetIndexExt};
-use lance_index::scalar::{InvertedIndexParams, FullTextSearchQuery};
-use arrow_array::{RecordBatch, RecordBatchIterator, Float32Array};
+use lance_index::optimize::OptimizeOptions;
+use lance_index::scalar::FullTextSearchQuery;
+use arrow_array::{ArrayRef, RecordBatch, RecordBatchIterator, Float32Array, UInt8Array};
 use futures::stream::TryStreamExt;
+use datafusion::common::ParamValues;
+use datafusion::execution::context::{SQLOptions, SessionContext};
+use lance::datafusion::LanceTableProvider;
 
-use crate::schema::build_arrow_schema;
+use crate::schema::{build_arrow_schema, parse_field_type};
 use crate::conversion::{build_record_batch, convert_batch_to_ruby};
-use arrow_schema::DataType;
+use crate::options::{build_compaction_options, build_optimize_options, build_write_params, get_opt, get_opt_string, get_opt_time_micros, value_to_string};
+use crate::checkpoint::{CheckpointManifest, FileCheckpointStore};
+use crate::diff::diff_versions;
+use crate::cleanup::{list_files, plan_cleanup};
+use crate::index::{build_inverted_index_params, build_scalar_index_params, build_vector_index_params, index_name_and_replace, resolve_metric};
+use crate::schema::METRIC_META_KEY;
+use crate::hybrid::{collect_candidates, fill_distances, parse_fusion, rank, search_hits, take_in_order, Fusion};
+use crate::query::parse_fts_query;
+use crate::sql::{dollar_placeholder, number_placeholders, parse_params, SELF_TABLE};
+use crate::scanner::{apply_ann_options, apply_filter, apply_filter_options, execute, filter_params, apply_order_by, apply_projection, offset_option, projected_columns, ScanOutput};
+use lance_linalg::distance::MetricType;
+use std::collections::HashSet;
+use arrow_schema::{ArrowError, DataType, Field, Schema as ArrowSchema};
 
 /// Convert Arrow DataType to Ruby-friendly string representation
 fn datatype_to_ruby_string(dtype: &DataType) -> &'static str {
@@ -32,11 +51,200 @@ fn datatype_to_ruby_string(dtype: &DataType) -> &'static str {
     }
 }
 
+/// Convert a Ruby query vector to an Arrow array matching the column's element type
+fn query_vector_array(schema: &ArrowSchema, column: &str, query_vector: RArray) -> Result<ArrayRef, Error> {
+    // Binary vectors are queried with bytes, everything else with float32
+    let is_binary = matches!(
+        schema.field_with_name(column).map(|f| f.data_type()),
+        Ok(DataType::FixedSizeList(inner, _)) if inner.data_type() == &DataType::UInt8
+    );
+    if is_binary {
+        let bytes: Vec<u8> = query_vector
+            .into_iter()
+            .map(|v| u8::try_convert(v))
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(Arc::new(UInt8Array::from(bytes)))
+    } else {
+        // Convert Ruby array to Vec<f32>
+        let vector: Vec<f32> = query_vector
+            .into_iter()
+            .map(|v| f64::try_convert(v).map(|f| f as f32))
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(Arc::new(Float32Array::from(vector)))
+    }
+}
+
+/// Call a Ruby block with a batch of documents and build a single-column batch from its results
+fn compute_column_batch(ruby: &Ruby, block: Proc, batch: &RecordBatch, schema: &ArrowSchema) -> Result<RecordBatch, Error> {
+    let documents = convert_batch_to_ruby(batch)?;
+    let values: RArray = block.call((documents,))?;
+
+    if values.len() != batch.num_rows() {
+        return Err(Error::new(
+            magnus::exception::arg_error(),
+            format!("Block returned {} values for a batch of {} rows", values.len(), batch.num_rows())
+        ));
+    }
+
+    let field = &schema.fields()[0];
+    let rows = ruby.ary_new_capa(values.len());
+    for value in values.into_iter() {
+        let row = ruby.hash_new();
+        row.aset(Symbol::new(field.name()), value)?;
+        rows.push(row)?;
+    }
+
+    build_record_batch(rows, schema)
+}
+
+/// Files as an array of `{ path:, size: }` hashes
+fn files_to_ruby(files: Vec<(String, u64)>) -> Result<RArray, Error> {
+    let ruby = Ruby::get().unwrap();
+    let array = ruby.ary_new();
+    for (path, size) in files {
+        let file = ruby.hash_new();
+        file.aset(Symbol::new("path"), path)?;
+        file.aset(Symbol::new("size"), size)?;
+        array.push(file)?;
+    }
+    Ok(array)
+}
+
+/// Dataset config key holding the `auto_optimize_indexes` threshold
+const AUTO_OPTIMIZE_CONFIG_KEY: &str = "lancelot.auto_optimize_indexes";
+
+fn auto_optimize_threshold(dataset: &Dataset) -> Option<usize> {
+    dataset.manifest().config.get(AUTO_OPTIMIZE_CONFIG_KEY)?.parse().ok()
+}
+
+/// File layout options given to `create`, which later appends use unless
+/// they give their own. Each is stored in the dataset config under
+/// `lancelot.write.<option>`.
+const STORED_WRITE_OPTIONS: [&str; 3] = ["max_rows_per_file", "max_rows_per_group", "max_bytes_per_file"];
+
+fn write_option_config_key(option: &str) -> String {
+    format!("lancelot.write.{}", option)
+}
+
+/// The stored layout options of `options`, as dataset config entries
+fn stored_write_config(options: Option<RHash>) -> Result<Vec<(String, String)>, Error> {
+    let Some(options) = options else {
+        return Ok(Vec::new());
+    };
+
+    let mut config = Vec::new();
+    for option in STORED_WRITE_OPTIONS {
+        if let Some(value) = get_opt::<usize>(&options, option)? {
+            config.push((write_option_config_key(option), value.to_string()));
+        }
+    }
+    Ok(config)
+}
+
+/// An append's write options, with the layout options stored by `create`
+/// filling in any it does not give
+fn with_stored_write_options(dataset: &Dataset, options: Option<RHash>) -> Result<Option<RHash>, Error> {
+    let config = &dataset.manifest().config;
+    let merged = RHash::new();
+    for option in STORED_WRITE_OPTIONS {
+        if let Some(value) = config.get(&write_option_config_key(option)).and_then(|value| value.parse::<usize>().ok()) {
+            merged.aset(Symbol::new(option), value)?;
+        }
+    }
+
+    if let Some(options) = options {
+        options.foreach(|key: Value, value: Value| {
+            merged.aset(key, value)?;
+            Ok(ForEach::Continue)
+        })?;
+    }
+
+    Ok(Some(merged))
+}
+
+/// Optimize the indexes with more unindexed rows than the dataset's
+/// `auto_optimize_indexes` threshold, if one is set. Unindexed rows are
+/// counted from fragment metadata, so no index is opened to check.
+async fn optimize_stale_indexes(dataset: &mut Dataset) -> lance::Result<()> {
+    let Some(threshold) = auto_optimize_threshold(dataset) else {
+        return Ok(());
+    };
+
+    let mut names: Vec<String> = dataset.load_indices()
+        .await?
+        .iter()
+        .filter(|index| !index.name.starts_with("__"))
+        .map(|index| index.name.clone())
+        .collect();
+    names.sort();
+    names.dedup();
+
+    let mut stale = Vec::new();
+    for name in names {
+        let unindexed: usize = dataset.unindexed_fragments(&name)
+            .await?
+            .iter()
+            .map(|fragment| fragment.num_rows().unwrap_or_default())
+            .sum();
+        if unindexed > threshold {
+            stale.push(name);
+        }
+    }
+
+    if stale.is_empty() {
+        return Ok(());
+    }
+    let options = OptimizeOptions { index_names: Some(stale), ..Default::default() };
+    dataset.optimize_indices(&options).await
+}
+
+/// Rows as an array of hashes, or an explained plan as a string
+fn scan_output_to_ruby(output: ScanOutput) -> Result<Value, Error> {
+    match output {
+        ScanOutput::Plan(plan) => Ok(RString::new(&plan).as_value()),
+        ScanOutput::Batches(batches) => {
+            let ruby = Ruby::get().unwrap();
+            let result_array = ruby.ary_new();
+            for batch in batches {
+                let batch_docs = convert_batch_to_ruby(&batch)?;
+                for i in 0..batch_docs.len() {
+                    result_array.push(batch_docs.entry::<Value>(i as isize)?)?;
+                }
+            }
+            Ok(result_array.as_value())
+        }
+    }
+}
+
+/// Find the latest version committed at or before the given time (microseconds since the epoch)
+async fn resolve_version_as_of(dataset: &Dataset, as_of_micros: i64) -> Result<u64, Error> {
+    let versions = dataset.versions()
+        .await
+        .map_err(|e| Error::new(magnus::exception::runtime_error(), e.to_string()))?;
+
+    versions.iter()
+        .filter(|version| version.timestamp.timestamp_micros() <= as_of_micros)
+        .map(|version| version.version)
+        .max()
+        .ok_or_else(|| Error::new(
+            magnus::exception::arg_error(),
+            "No version of the dataset was committed at or before the given time"
+        ))
+}
+
 #[magnus::wrap(class = "Lancelot::Dataset", free_immediately, size)]
 pub struct LancelotDataset {
     dataset: RefCell<Option<Dataset>>,
     runtime: RefCell<Runtime>,
     path: String,
+    read_only: Cell<bool>,
+}
+
+fn in_use_error() -> Error {
+    Error::new(
+        magnus::exception::runtime_error(),
+        "Dataset is in use by an add_column block and cannot be used from inside it"
+    )
 }
 
 impl LancelotDataset {
@@ -48,15 +256,67 @@ impl LancelotDataset {
             dataset: RefCell::new(None),
             runtime: RefCell::new(runtime),
             path,
+            read_only: Cell::new(false),
+        })
+    }
+
+    // An add_column block runs while the dataset and runtime are borrowed, so
+    // calls back into the dataset from the block raise instead of panicking
+    fn dataset_ref(&self) -> Result<Ref<'_, Option<Dataset>>, Error> {
+        self.dataset.try_borrow().map_err(|_| in_use_error())
+    }
+
+    fn dataset_mut(&self) -> Result<RefMut<'_, Option<Dataset>>, Error> {
+        self.dataset.try_borrow_mut().map_err(|_| in_use_error())
+    }
+
+    fn runtime(&self) -> Result<RefMut<'_, Runtime>, Error> {
+        self.runtime.try_borrow_mut().map_err(|_| in_use_error())
+    }
+
+    /// Check out the `version` in the read options, or the version that was
+    /// current at the `as_of` time, if either is given
+    fn as_of_snapshot(&self, dataset: &Dataset, options: &Option<RHash>) -> Result<Option<Dataset>, Error> {
+        let (version, as_of) = match options {
+            Some(options) => (get_opt::<u64>(options, "version")?, get_opt_time_micros(options, "as_of")?),
+            None => (None, None),
+        };
+        if version.is_none() && as_of.is_none() {
+            return Ok(None);
+        }
+
+        self.runtime()?.block_on(async {
+            let version = match version {
+                Some(version) => version,
+                None => resolve_version_as_of(dataset, as_of.unwrap_or_default()).await?,
+            };
+            dataset.checkout_version(version)
+                .await
+                .map(Some)
+                .map_err(|e| Error::new(magnus::exception::runtime_error(), e.to_string()))
         })
     }
 
+    /// Historical versions are opened read-only; writes must go through the latest version
+    fn check_writable(&self) -> Result<(), Error> {
+        if self.read_only.get() {
+            let version = self.dataset_ref()?.as_ref().map(|d| d.version().version);
+            return Err(Error::new(
+                magnus::exception::runtime_error(),
+                format!("Dataset is a read-only checkout of version {}", version.unwrap_or_default())
+            ));
+        }
+        Ok(())
+    }
+
     pub fn path(&self) -> String {
         self.path.clone()
     }
 
-    pub fn create(&self, schema_hash: RHash) -> Result<(), Error> {
+    pub fn create(&self, schema_hash: RHash, options: Option<RHash>) -> Result<(), Error> {
         let schema = build_arrow_schema(schema_hash)?;
+        let stored_config = stored_write_config(options)?;
+        let params = build_write_params(options)?;
         
         let empty_batch = RecordBatch::new_empty(Arc::new(schema.clone()));
         let batches = vec![empty_batch];
@@ -65,33 +325,370 @@ impl LancelotDataset {
             Arc::new(schema)
         );
         
-        let dataset = self.runtime.borrow_mut().block_on(async {
-            Dataset::write(
+        let dataset = self.runtime()?.block_on(async {
+            let mut dataset = Dataset::write(
                 reader,
                 &self.path,
-                None,
+                Some(params),
             )
             .await
-            .map_err(|e| Error::new(magnus::exception::runtime_error(), e.to_string()))
+            .map_err(|e| Error::new(magnus::exception::runtime_error(), e.to_string()))?;
+
+            // Keep the layout options for later appends
+            if !stored_config.is_empty() {
+                dataset.update_config(stored_config)
+                    .await
+                    .map_err(|e| Error::new(magnus::exception::runtime_error(), e.to_string()))?;
+            }
+            Ok::<_, Error>(dataset)
         })?;
 
-        self.dataset.replace(Some(dataset));
+        *self.dataset_mut()? = Some(dataset);
         Ok(())
     }
 
-    pub fn open(&self) -> Result<(), Error> {
-        let dataset = self.runtime.borrow_mut().block_on(async {
-            Dataset::open(&self.path)
+    pub fn open(&self, options: Option<RHash>) -> Result<(), Error> {
+        let (version, tag, as_of): (Option<u64>, Option<String>, Option<i64>) = match options {
+            Some(options) => (
+                get_opt(&options, "version")?,
+                get_opt_string(&options, "tag")?,
+                get_opt_time_micros(&options, "as_of")?,
+            ),
+            None => (None, None, None),
+        };
+
+        let dataset = self.runtime()?.block_on(async {
+            let dataset = Dataset::open(&self.path)
+                .await
+                .map_err(|e| Error::new(magnus::exception::runtime_error(), e.to_string()))?;
+
+            let checkout = match (version, &tag, as_of) {
+                (Some(version), None, None) => dataset.checkout_version(version).await,
+                (None, Some(tag), None) => dataset.checkout_version(tag.as_str()).await,
+                (None, None, Some(as_of)) => {
+                    let version = resolve_version_as_of(&dataset, as_of).await?;
+                    dataset.checkout_version(version).await
+                }
+                (None, None, None) => Ok(dataset),
+                _ => return Err(Error::new(
+                    magnus::exception::arg_error(),
+                    "Only one of version, tag and as_of can be given"
+                )),
+            };
+
+            checkout.map_err(|e| Error::new(magnus::exception::runtime_error(), e.to_string()))
+        })?;
+
+        *self.dataset_mut()? = Some(dataset);
+        self.read_only.set(version.is_some() || tag.is_some() || as_of.is_some());
+        Ok(())
+    }
+
+    pub fn is_read_only(&self) -> bool {
+        self.read_only.get()
+    }
+
+    pub fn version(&self) -> Result<u64, Error> {
+        let dataset = self.dataset_ref()?;
+        let dataset = dataset.as_ref()
+            .ok_or_else(|| Error::new(magnus::exception::runtime_error(), "Dataset not opened"))?;
+
+        Ok(dataset.version().version)
+    }
+
+    pub fn versions(&self) -> Result<RArray, Error> {
+        let dataset = self.dataset_ref()?;
+        let dataset = dataset.as_ref()
+            .ok_or_else(|| Error::new(magnus::exception::runtime_error(), "Dataset not opened"))?;
+
+        let details = self.runtime()?.block_on(async {
+            let versions = dataset.versions()
+                .await
+                .map_err(|e| Error::new(magnus::exception::runtime_error(), e.to_string()))?;
+
+            // The listing carries each manifest's summary, so only the
+            // transaction file is read per version, without opening it
+            let mut details = Vec::with_capacity(versions.len());
+            for version in versions {
+                let operation = dataset.read_transaction_by_version(version.version)
+                    .await
+                    .map_err(|e| Error::new(magnus::exception::runtime_error(), e.to_string()))?
+                    .map(|transaction| transaction.operation.name().to_string());
+                let num_rows = match version.metadata.get("total_rows").and_then(|rows| rows.parse::<usize>().ok()) {
+                    Some(num_rows) => num_rows,
+                    // Manifests too old to summarise their row count
+                    None => dataset.checkout_version(version.version)
+                        .await
+                        .map_err(|e| Error::new(magnus::exception::runtime_error(), e.to_string()))?
+                        .count_rows(None)
+                        .await
+                        .map_err(|e| Error::new(magnus::exception::runtime_error(), e.to_string()))?,
+                };
+
+                details.push((version, operation, num_rows));
+            }
+
+            Ok::<_, Error>(details)
+        })?;
+
+        let ruby = Ruby::get().unwrap();
+        let result_array = ruby.ary_new();
+
+        for (version, operation, num_rows) in details {
+            let info = ruby.hash_new();
+            info.aset(Symbol::new("version"), version.version)?;
+            info.aset(Symbol::new("timestamp_micros"), version.timestamp.timestamp_micros())?;
+            info.aset(Symbol::new("operation"), operation)?;
+            info.aset(Symbol::new("num_rows"), num_rows as i64)?;
+            result_array.push(info)?;
+        }
+
+        Ok(result_array)
+    }
+
+    pub fn fragments(&self) -> Result<RArray, Error> {
+        let dataset = self.dataset_ref()?;
+        let dataset = dataset.as_ref()
+            .ok_or_else(|| Error::new(magnus::exception::runtime_error(), "Dataset not opened"))?;
+
+        let ruby = Ruby::get().unwrap();
+        let result_array = ruby.ary_new();
+
+        for fragment in dataset.manifest().fragments.iter() {
+            let info = ruby.hash_new();
+            info.aset(Symbol::new("id"), fragment.id)?;
+            info.aset(Symbol::new("num_rows"), fragment.num_rows().map(|rows| rows as i64))?;
+            let files = ruby.ary_new();
+            for file in &fragment.files {
+                files.push(file.path.clone())?;
+            }
+            info.aset(Symbol::new("files"), files)?;
+            result_array.push(info)?;
+        }
+
+        Ok(result_array)
+    }
+
+    pub fn checkout(&self, version: u64) -> Result<LancelotDataset, Error> {
+        let dataset = self.dataset_ref()?;
+        let dataset = dataset.as_ref()
+            .ok_or_else(|| Error::new(magnus::exception::runtime_error(), "Dataset not opened"))?;
+
+        let snapshot = self.runtime()?.block_on(async {
+            dataset.checkout_version(version)
+                .await
+                .map_err(|e| Error::new(magnus::exception::runtime_error(), e.to_string()))
+        })?;
+
+        let checkout = LancelotDataset::new(self.path.clone())?;
+        checkout.dataset.replace(Some(snapshot));
+        checkout.read_only.set(true);
+        Ok(checkout)
+    }
+
+    pub fn create_tag(&self, name: String, version: u64) -> Result<(), Error> {
+        let mut dataset = self.dataset_mut()?;
+        let dataset = dataset.as_mut()
+            .ok_or_else(|| Error::new(magnus::exception::runtime_error(), "Dataset not opened"))?;
+
+        self.runtime()?.block_on(async move {
+            dataset.tags.create(&name, version)
+                .await
+                .map_err(|e| Error::new(magnus::exception::runtime_error(), e.to_string()))
+        })
+    }
+
+    pub fn delete_tag(&self, name: String) -> Result<(), Error> {
+        let mut dataset = self.dataset_mut()?;
+        let dataset = dataset.as_mut()
+            .ok_or_else(|| Error::new(magnus::exception::runtime_error(), "Dataset not opened"))?;
+
+        self.runtime()?.block_on(async move {
+            dataset.tags.delete(&name)
+                .await
+                .map_err(|e| Error::new(magnus::exception::runtime_error(), e.to_string()))
+        })
+    }
+
+    pub fn tags(&self) -> Result<RHash, Error> {
+        let dataset = self.dataset_ref()?;
+        let dataset = dataset.as_ref()
+            .ok_or_else(|| Error::new(magnus::exception::runtime_error(), "Dataset not opened"))?;
+
+        let tags = self.runtime()?.block_on(async {
+            dataset.tags.list()
+                .await
+                .map_err(|e| Error::new(magnus::exception::runtime_error(), e.to_string()))
+        })?;
+
+        let ruby = Ruby::get().unwrap();
+        let hash = ruby.hash_new();
+        for (name, contents) in tags {
+            hash.aset(name, contents.version)?;
+        }
+
+        Ok(hash)
+    }
+
+    pub fn diff(&self, from_version: u64, to_version: u64) -> Result<RHash, Error> {
+        let dataset = self.dataset_ref()?;
+        let dataset = dataset.as_ref()
+            .ok_or_else(|| Error::new(magnus::exception::runtime_error(), "Dataset not opened"))?;
+
+        let diff = self.runtime()?.block_on(async {
+            let from = dataset.checkout_version(from_version)
+                .await
+                .map_err(|e| Error::new(magnus::exception::runtime_error(), e.to_string()))?;
+            let to = dataset.checkout_version(to_version)
+                .await
+                .map_err(|e| Error::new(magnus::exception::runtime_error(), e.to_string()))?;
+
+            diff_versions(&from, &to)
                 .await
                 .map_err(|e| Error::new(magnus::exception::runtime_error(), e.to_string()))
         })?;
 
-        self.dataset.replace(Some(dataset));
+        let ruby = Ruby::get().unwrap();
+        let hash = ruby.hash_new();
+
+        for (name, batches) in [("removed", diff.removed), ("added", diff.added)] {
+            let documents = ruby.ary_new();
+            for batch in batches {
+                let batch_docs = convert_batch_to_ruby(&batch)?;
+                // Merge arrays by pushing each element
+                for i in 0..batch_docs.len() {
+                    documents.push(batch_docs.entry::<Value>(i as isize)?)?;
+                }
+            }
+            hash.aset(Symbol::new(name), documents)?;
+        }
+
+        Ok(hash)
+    }
+
+    pub fn cleanup(&self, older_than_seconds: f64, options: RHash) -> Result<RHash, Error> {
+        self.check_writable()?;
+        let dataset = self.dataset_ref()?;
+        let dataset = dataset.as_ref()
+            .ok_or_else(|| Error::new(magnus::exception::runtime_error(), "Dataset not opened"))?;
+
+        let older_than = chrono::Duration::milliseconds((older_than_seconds * 1000.0) as i64);
+        let delete_unverified = get_opt::<bool>(&options, "delete_unverified")?.unwrap_or(false);
+        let error_if_tagged = get_opt::<bool>(&options, "error_if_tagged")?.unwrap_or(false);
+
+        // Lance never removes tagged versions; it either keeps them quietly or raises
+        let (stats, removed_files) = self.runtime()?.block_on(async {
+            let before = list_files(dataset)
+                .await
+                .map_err(|e| Error::new(magnus::exception::runtime_error(), e.to_string()))?;
+            let stats = dataset.cleanup_old_versions(older_than, Some(delete_unverified), Some(error_if_tagged))
+                .await
+                .map_err(|e| Error::new(magnus::exception::runtime_error(), e.to_string()))?;
+            let after = list_files(dataset)
+                .await
+                .map_err(|e| Error::new(magnus::exception::runtime_error(), e.to_string()))?;
+
+            let mut removed: Vec<(String, u64)> = before.into_iter()
+                .filter(|(path, _)| !after.contains_key(path))
+                .collect();
+            removed.sort();
+            Ok::<_, Error>((stats, removed))
+        })?;
+
+        let ruby = Ruby::get().unwrap();
+        let hash = ruby.hash_new();
+        hash.aset(Symbol::new("bytes_removed"), stats.bytes_removed)?;
+        hash.aset(Symbol::new("versions_removed"), stats.old_versions)?;
+        hash.aset(Symbol::new("files"), files_to_ruby(removed_files)?)?;
+
+        Ok(hash)
+    }
+
+    pub fn cleanup_plan(&self, older_than_seconds: f64, options: RHash) -> Result<RHash, Error> {
+        let dataset = self.dataset_ref()?;
+        let dataset = dataset.as_ref()
+            .ok_or_else(|| Error::new(magnus::exception::runtime_error(), "Dataset not opened"))?;
+
+        let cutoff = chrono::Utc::now() - chrono::Duration::milliseconds((older_than_seconds * 1000.0) as i64);
+        let delete_unverified = get_opt::<bool>(&options, "delete_unverified")?.unwrap_or(false);
+        let error_if_tagged = get_opt::<bool>(&options, "error_if_tagged")?.unwrap_or(false);
+
+        let plan = self.runtime()?.block_on(async {
+            let tagged: HashSet<u64> = dataset.tags.list()
+                .await
+                .map_err(|e| Error::new(magnus::exception::runtime_error(), e.to_string()))?
+                .into_values()
+                .map(|contents| contents.version)
+                .collect();
+            plan_cleanup(dataset, cutoff, &tagged, delete_unverified)
+                .await
+                .map_err(|e| Error::new(magnus::exception::runtime_error(), e.to_string()))
+        })?;
+
+        if error_if_tagged && !plan.tagged_versions.is_empty() {
+            return Err(Error::new(
+                magnus::exception::runtime_error(),
+                format!("Cleanup would remove tagged versions {:?}; delete their tags first", plan.tagged_versions)
+            ));
+        }
+
+        let ruby = Ruby::get().unwrap();
+        let hash = ruby.hash_new();
+        hash.aset(Symbol::new("versions"), plan.versions)?;
+        hash.aset(Symbol::new("files"), files_to_ruby(plan.files)?)?;
+
+        Ok(hash)
+    }
+
+    pub fn compact(&self, options: RHash) -> Result<RHash, Error> {
+        self.check_writable()?;
+        let mut dataset = self.dataset_mut()?;
+        let dataset = dataset.as_mut()
+            .ok_or_else(|| Error::new(magnus::exception::runtime_error(), "Dataset not opened"))?;
+
+        let compaction = build_compaction_options(&options)?;
+
+        // Without explicit remap options Lance remaps existing indexes to the rewritten fragments
+        let metrics = self.runtime()?.block_on(async move {
+            compact_files(dataset, compaction, None)
+                .await
+                .map_err(|e| Error::new(magnus::exception::runtime_error(), e.to_string()))
+        })?;
+
+        let ruby = Ruby::get().unwrap();
+        let hash = ruby.hash_new();
+        hash.aset(Symbol::new("fragments_removed"), metrics.fragments_removed)?;
+        hash.aset(Symbol::new("fragments_added"), metrics.fragments_added)?;
+        hash.aset(Symbol::new("files_removed"), metrics.files_removed)?;
+        hash.aset(Symbol::new("files_added"), metrics.files_added)?;
+
+        Ok(hash)
+    }
+
+    pub fn restore(&self, version: u64) -> Result<(), Error> {
+        self.check_writable()?;
+        let mut dataset = self.dataset_mut()?;
+        let current = dataset.as_ref()
+            .ok_or_else(|| Error::new(magnus::exception::runtime_error(), "Dataset not opened"))?;
+
+        // Restoring commits the old manifest as a new latest version
+        let restored = self.runtime()?.block_on(async {
+            let mut snapshot = current.checkout_version(version)
+                .await
+                .map_err(|e| Error::new(magnus::exception::runtime_error(), e.to_string()))?;
+            snapshot.restore()
+                .await
+                .map_err(|e| Error::new(magnus::exception::runtime_error(), e.to_string()))?;
+            Ok::<_, Error>(snapshot)
+        })?;
+
+        *dataset = Some(restored);
         Ok(())
     }
 
-    pub fn add_data(&self, data: RArray) -> Result<(), Error> {
-        let mut dataset = self.dataset.borrow_mut();
+    pub fn add_data(&self, data: RArray, options: Option<RHash>) -> Result<(), Error> {
+        self.check_writable()?;
+        let mut dataset = self.dataset_mut()?;
         let dataset = dataset.as_mut()
             .ok_or_else(|| Error::new(magnus::exception::runtime_error(), "Dataset not opened"))?;
 
@@ -101,7 +698,7 @@ impl LancelotDataset {
         }
 
         // Get the dataset's schema
-        let schema = self.runtime.borrow_mut().block_on(async {
+        let schema = self.runtime()?.block_on(async {
             dataset.schema()
         });
         
@@ -109,6 +706,7 @@ impl LancelotDataset {
         let arrow_schema = schema.into();
 
         let batch = build_record_batch(data, &arrow_schema)?;
+        let params = build_write_params(with_stored_write_options(dataset, options)?)?;
 
         let batches = vec![batch];
         let reader = RecordBatchIterator::new(
@@ -116,21 +714,59 @@ impl LancelotDataset {
             Arc::new(arrow_schema)
         );
         
-        self.runtime.borrow_mut().block_on(async move {
-            dataset.append(reader, None)
+        let optimized = self.runtime()?.block_on(async move {
+            dataset.append(reader, Some(params))
                 .await
-                .map_err(|e| Error::new(magnus::exception::runtime_error(), e.to_string()))
+                .map_err(|e| Error::new(magnus::exception::runtime_error(), e.to_string()))?;
+            Ok::<_, Error>(optimize_stale_indexes(dataset).await)
         })?;
 
+        // The rows are committed by now, so raising would invite a retry that
+        // appends them twice; the indexes are left for a later append or
+        // optimize_indexes instead
+        if let Err(e) = optimized {
+            let ruby = Ruby::get().unwrap();
+            let _: Value = ruby.module_kernel().funcall(
+                "warn",
+                (format!("Lancelot: automatic index optimization failed: {}", e),),
+            )?;
+        }
+
         Ok(())
     }
 
+    /// The unindexed row count above which appends optimize an index, stored
+    /// in the dataset's config so every handle on the dataset shares it
+    pub fn auto_optimize_indexes(&self) -> Result<Option<usize>, Error> {
+        let dataset = self.dataset_ref()?;
+        let dataset = dataset.as_ref()
+            .ok_or_else(|| Error::new(magnus::exception::runtime_error(), "Dataset not opened"))?;
+
+        Ok(auto_optimize_threshold(dataset))
+    }
+
+    pub fn set_auto_optimize_indexes(&self, threshold: Option<usize>) -> Result<(), Error> {
+        self.check_writable()?;
+        let mut dataset = self.dataset_mut()?;
+        let dataset = dataset.as_mut()
+            .ok_or_else(|| Error::new(magnus::exception::runtime_error(), "Dataset not opened"))?;
+
+        self.runtime()?.block_on(async move {
+            match threshold {
+                Some(threshold) => {
+                    dataset.update_config([(AUTO_OPTIMIZE_CONFIG_KEY.to_string(), threshold.to_string())]).await
+                }
+                None => dataset.delete_config_keys(&[AUTO_OPTIMIZE_CONFIG_KEY]).await,
+            }
+        }).map_err(|e| Error::new(magnus::exception::runtime_error(), e.to_string()))
+    }
+
     pub fn count_rows(&self) -> Result<i64, Error> {
-        let dataset = self.dataset.borrow();
+        let dataset = self.dataset_ref()?;
         let dataset = dataset.as_ref()
             .ok_or_else(|| Error::new(magnus::exception::runtime_error(), "Dataset not opened"))?;
 
-        let count = self.runtime.borrow_mut().block_on(async {
+        let count = self.runtime()?.block_on(async {
             dataset.count_rows(None)
                 .await
                 .map_err(|e| Error::new(magnus::exception::runtime_error(), e.to_string()))
@@ -140,12 +776,12 @@ impl LancelotDataset {
     }
 
     pub fn schema(&self) -> Result<RHash, Error> {
-        let dataset = self.dataset.borrow();
+        let dataset = self.dataset_ref()?;
         let dataset = dataset.as_ref()
             .ok_or_else(|| Error::new(magnus::exception::runtime_error(), "Dataset not opened"))?;
 
         // Get the actual schema from the Lance dataset
-        let schema = self.runtime.borrow_mut().block_on(async {
+        let schema = self.runtime()?.block_on(async {
             dataset.schema()
         });
         
@@ -163,15 +799,31 @@ impl LancelotDataset {
             // Handle vector columns specially
             if let DataType::FixedSizeList(inner_field, dimension) = field.data_type() {
                 // Check if it's a vector (float list)
-                if matches!(inner_field.data_type(), DataType::Float32 | DataType::Float16) {
+                if matches!(inner_field.data_type(), DataType::Float32 | DataType::Float16 | DataType::UInt8) {
                     let vector_info = ruby.hash_new();
                     vector_info.aset(Symbol::new("type"), "vector")?;
                     vector_info.aset(Symbol::new("dimension"), *dimension)?;
+                    match inner_field.data_type() {
+                        DataType::Float16 => vector_info.aset(Symbol::new("element_type"), "float16")?,
+                        DataType::UInt8 => vector_info.aset(Symbol::new("element_type"), "uint8")?,
+                        _ => {}
+                    }
+                    if let Some(metric) = field.metadata().get(METRIC_META_KEY) {
+                        vector_info.aset(Symbol::new("metric"), metric.as_str())?;
+                    }
                     hash.aset(field_name, vector_info)?;
                     continue;
                 }
             }
             
+            if let DataType::List(inner_field) = field.data_type() {
+                let list_info = ruby.hash_new();
+                list_info.aset(Symbol::new("type"), "list")?;
+                list_info.aset(Symbol::new("element_type"), datatype_to_ruby_string(inner_field.data_type()))?;
+                hash.aset(field_name, list_info)?;
+                continue;
+            }
+            
             let field_type = datatype_to_ruby_string(field.data_type());
             hash.aset(field_name, field_type)?;
         }
@@ -179,13 +831,20 @@ impl LancelotDataset {
         Ok(hash)
     }
 
-    pub fn scan_all(&self) -> Result<RArray, Error> {
-        let dataset = self.dataset.borrow();
+    pub fn scan_all(&self, options: Option<RHash>) -> Result<RArray, Error> {
+        let dataset = self.dataset_ref()?;
         let dataset = dataset.as_ref()
             .ok_or_else(|| Error::new(magnus::exception::runtime_error(), "Dataset not opened"))?;
 
-        let batches: Vec<RecordBatch> = self.runtime.borrow_mut().block_on(async {
-            let scanner = dataset.scan();
+        let arrow_schema: ArrowSchema = dataset.schema().into();
+
+        let batches: Vec<RecordBatch> = self.runtime()?.block_on(async {
+            let mut scanner = dataset.scan();
+            if let Some(options) = &options {
+                apply_projection(&mut scanner, &arrow_schema, options)?;
+                apply_order_by(&mut scanner, &arrow_schema, options)?;
+            }
+            
             let stream = scanner
                 .try_into_stream()
                 .await
@@ -211,143 +870,291 @@ impl LancelotDataset {
         Ok(result_array)
     }
 
-    pub fn scan_limit(&self, limit: i64) -> Result<RArray, Error> {
-        let dataset = self.dataset.borrow();
+    pub fn scan_limit(&self, limit: i64, options: Option<RHash>) -> Result<Value, Error> {
+        let dataset = self.dataset_ref()?;
         let dataset = dataset.as_ref()
             .ok_or_else(|| Error::new(magnus::exception::runtime_error(), "Dataset not opened"))?;
 
-        let batches: Vec<RecordBatch> = self.runtime.borrow_mut().block_on(async {
+        let snapshot = self.as_of_snapshot(dataset, &options)?;
+        let dataset = snapshot.as_ref().unwrap_or(dataset);
+
+        let arrow_schema: ArrowSchema = dataset.schema().into();
+
+        let offset = offset_option(options.as_ref())?;
+
+        let output = self.runtime()?.block_on(async {
             let mut scanner = dataset.scan();
-            scanner.limit(Some(limit), None)
+            scanner.limit(Some(limit), offset)
                 .map_err(|e| Error::new(magnus::exception::runtime_error(), e.to_string()))?;
+            if let Some(options) = &options {
+                apply_projection(&mut scanner, &arrow_schema, options)?;
+                apply_order_by(&mut scanner, &arrow_schema, options)?;
+            }
             
-            let stream = scanner
-                .try_into_stream()
-                .await
+            execute(&scanner, options.as_ref()).await
+        })?;
+
+        scan_output_to_ruby(output)
+    }
+
+    pub fn create_vector_index(&self, column: String, options: RHash) -> Result<(), Error> {
+        self.check_writable()?;
+        let mut dataset = self.dataset_mut()?;
+        let dataset = dataset.as_mut()
+            .ok_or_else(|| Error::new(magnus::exception::runtime_error(), "Dataset not opened"))?;
+
+        // Defaults for partitions and sub-vectors are derived from the vector dimension
+        let arrow_schema: ArrowSchema = dataset.schema().into();
+        let metric = resolve_metric(Some(&options), &arrow_schema, &column)?.unwrap_or(MetricType::L2);
+        let dimension = match arrow_schema.field_with_name(&column).map(|f| f.data_type()) {
+            Ok(DataType::FixedSizeList(_, dimension)) => *dimension as usize,
+            Ok(_) => return Err(Error::new(
+                magnus::exception::arg_error(),
+                format!("Column {} is not a vector column", column)
+            )),
+            Err(e) => return Err(Error::new(magnus::exception::arg_error(), e.to_string())),
+        };
+
+        let (name, replace) = index_name_and_replace(&options)?;
+
+        self.runtime()?.block_on(async move {
+            // Get row count to determine optimal number of partitions
+            let num_rows = dataset.count_rows(None).await
                 .map_err(|e| Error::new(magnus::exception::runtime_error(), e.to_string()))?;
             
-            stream
-                .try_collect::<Vec<_>>()
-                .await
-                .map_err(|e| Error::new(magnus::exception::runtime_error(), e.to_string()))
-        })?;
+            let params = build_vector_index_params(&options, metric, num_rows, dimension)?;
+            
+            dataset.create_index(
+                &[&column],
+                IndexType::Vector,
+                name,
+                &params,
+                replace
+            )
+            .await
+            .map_err(|e| Error::new(magnus::exception::runtime_error(), e.to_string()))
+        })
+    }
 
-        let ruby = Ruby::get().unwrap();
-        let result_array = ruby.ary_new();
+    pub fn vector_search(&self, column: String, query_vector: RArray, limit: i64, options: Option<RHash>) -> Result<Value, Error> {
+        let dataset = self.dataset_ref()?;
+        let dataset = dataset.as_ref()
+            .ok_or_else(|| Error::new(magnus::exception::runtime_error(), "Dataset not opened"))?;
 
-        for batch in batches {
-            let batch_docs = convert_batch_to_ruby(&batch)?;
-            // Merge arrays by pushing each element
-            for i in 0..batch_docs.len() {
-                result_array.push(batch_docs.entry::<Value>(i as isize)?)?;
+        let snapshot = self.as_of_snapshot(dataset, &options)?;
+        let dataset = snapshot.as_ref().unwrap_or(dataset);
+
+        let arrow_schema: ArrowSchema = dataset.schema().into();
+        let metric = resolve_metric(options.as_ref(), &arrow_schema, &column)?;
+
+        let query = query_vector_array(&arrow_schema, &column, query_vector)?;
+        let offset = offset_option(options.as_ref())?;
+
+        let output = self.runtime()?.block_on(async {
+            let mut scanner = dataset.scan();
+            
+            // Use nearest for vector search, finding enough neighbours to skip the offset
+            let k = limit + offset.unwrap_or(0);
+            scanner.nearest(&column, query.as_ref(), k as usize)
+                .map_err(|e| Error::new(magnus::exception::runtime_error(), e.to_string()))?;
+            if offset.is_some() {
+                scanner.limit(Some(limit), offset)
+                    .map_err(|e| Error::new(magnus::exception::runtime_error(), e.to_string()))?;
             }
-        }
+            
+            if let Some(metric) = metric {
+                scanner.distance_metric(metric);
+            }
+            if let Some(options) = &options {
+                apply_ann_options(&mut scanner, options)?;
+                apply_filter_options(&mut scanner, &arrow_schema, options)?;
+                apply_projection(&mut scanner, &arrow_schema, options)?;
+            }
+            
+            execute(&scanner, options.as_ref()).await
+        })?;
 
-        Ok(result_array)
+        scan_output_to_ruby(output)
+    }
+
+    pub fn create_scalar_index(&self, column: String, options: RHash) -> Result<(), Error> {
+        self.check_writable()?;
+        let mut dataset = self.dataset_mut()?;
+        let dataset = dataset.as_mut()
+            .ok_or_else(|| Error::new(magnus::exception::runtime_error(), "Dataset not opened"))?;
+
+        let arrow_schema: ArrowSchema = dataset.schema().into();
+        let field = arrow_schema.field_with_name(&column)
+            .map_err(|_| Error::new(magnus::exception::arg_error(), format!("Column {} not found", column)))?;
+        let (index_type, params) = build_scalar_index_params(&options, &column, field.data_type())?;
+        let (name, replace) = index_name_and_replace(&options)?;
+
+        self.runtime()?.block_on(async move {
+            dataset.create_index(
+                &[&column],
+                index_type,
+                name,
+                &params,
+                replace
+            )
+            .await
+            .map_err(|e| Error::new(magnus::exception::runtime_error(), e.to_string()))
+        })
     }
 
-    pub fn create_vector_index(&self, column: String) -> Result<(), Error> {
-        let mut dataset = self.dataset.borrow_mut();
+    pub fn create_text_index(&self, column: String, options: RHash) -> Result<(), Error> {
+        self.check_writable()?;
+        let mut dataset = self.dataset_mut()?;
         let dataset = dataset.as_mut()
             .ok_or_else(|| Error::new(magnus::exception::runtime_error(), "Dataset not opened"))?;
 
-        self.runtime.borrow_mut().block_on(async move {
-            // Get row count to determine optimal number of partitions
-            let num_rows = dataset.count_rows(None).await
-                .map_err(|e| Error::new(magnus::exception::runtime_error(), e.to_string()))?;
-            
-            // Use fewer partitions for small datasets
-            let num_partitions = if num_rows < 256 {
-                std::cmp::max(1, (num_rows / 4) as usize)
-            } else {
-                256
-            };
-            
-            // Create IVF_FLAT vector index parameters
-            let params = VectorIndexParams::ivf_flat(num_partitions, lance_linalg::distance::MetricType::L2);
-            
+        let (name, replace) = index_name_and_replace(&options)?;
+        let params = build_inverted_index_params(&options)?;
+
+        self.runtime()?.block_on(async move {
+            // Create inverted index for full-text search
             dataset.create_index(
                 &[&column],
-                IndexType::Vector,
-                None,
+                IndexType::Inverted,
+                name,
                 &params,
-                true
+                replace
             )
             .await
             .map_err(|e| Error::new(magnus::exception::runtime_error(), e.to_string()))
         })
     }
 
-    pub fn vector_search(&self, column: String, query_vector: RArray, limit: i64) -> Result<RArray, Error> {
-        let dataset = self.dataset.borrow();
+    /// One entry per index name, with its columns and the uuids of every
+    /// delta built under that name
+    pub fn indexes(&self) -> Result<RArray, Error> {
+        let dataset = self.dataset_ref()?;
         let dataset = dataset.as_ref()
             .ok_or_else(|| Error::new(magnus::exception::runtime_error(), "Dataset not opened"))?;
 
-        // Convert Ruby array to Vec<f32>
-        let vector: Vec<f32> = query_vector
-            .into_iter()
-            .map(|v| f64::try_convert(v).map(|f| f as f32))
-            .collect::<Result<Vec<_>, _>>()?;
+        let indices = self.runtime()?.block_on(async {
+            dataset.load_indices().await
+        }).map_err(|e| Error::new(magnus::exception::runtime_error(), e.to_string()))?;
 
-        let batches: Vec<RecordBatch> = self.runtime.borrow_mut().block_on(async {
-            let mut scanner = dataset.scan();
-            
-            // Use nearest for vector search
-            scanner.nearest(&column, &Float32Array::from(vector), limit as usize)
-                .map_err(|e| Error::new(magnus::exception::runtime_error(), e.to_string()))?;
-            
-            let stream = scanner
-                .try_into_stream()
-                .await
-                .map_err(|e| Error::new(magnus::exception::runtime_error(), e.to_string()))?;
-            
-            stream
-                .try_collect::<Vec<_>>()
-                .await
-                .map_err(|e| Error::new(magnus::exception::runtime_error(), e.to_string()))
-        })?;
+        let schema = dataset.schema();
+        let mut grouped: Vec<(String, Vec<String>, Vec<String>)> = Vec::new();
 
-        let ruby = Ruby::get().unwrap();
-        let result_array = ruby.ary_new();
+        for index in indices.iter() {
+            // Lance keeps internal bookkeeping indexes under reserved names
+            if index.name.starts_with("__") {
+                continue;
+            }
 
-        for batch in batches {
-            let batch_docs = convert_batch_to_ruby(&batch)?;
-            // Merge arrays by pushing each element
-            for i in 0..batch_docs.len() {
-                result_array.push(batch_docs.entry::<Value>(i as isize)?)?;
+            let uuid = index.uuid.to_string();
+            match grouped.iter_mut().find(|(name, _, _)| name == &index.name) {
+                Some((_, _, uuids)) => uuids.push(uuid),
+                None => {
+                    let columns = index.fields.iter()
+                        .filter_map(|id| schema.field_by_id(*id))
+                        .map(|field| field.name.clone())
+                        .collect();
+                    grouped.push((index.name.clone(), columns, vec![uuid]));
+                }
             }
         }
 
-        Ok(result_array)
+        let ruby = Ruby::get().unwrap();
+        let result = ruby.ary_new();
+        for (name, columns, uuids) in grouped {
+            let hash = ruby.hash_new();
+            hash.aset(Symbol::new("name"), name)?;
+            hash.aset(Symbol::new("columns"), columns)?;
+            hash.aset(Symbol::new("uuids"), uuids)?;
+            result.push(hash)?;
+        }
+
+        Ok(result)
+    }
+
+    /// Lance's statistics for an index, as a JSON string
+    pub fn index_stats(&self, name: String) -> Result<String, Error> {
+        let dataset = self.dataset_ref()?;
+        let dataset = dataset.as_ref()
+            .ok_or_else(|| Error::new(magnus::exception::runtime_error(), "Dataset not opened"))?;
+
+        self.runtime()?.block_on(async {
+            dataset.index_statistics(&name).await
+        }).map_err(|e| Error::new(magnus::exception::runtime_error(), e.to_string()))
     }
 
-    pub fn create_text_index(&self, column: String) -> Result<(), Error> {
-        let mut dataset = self.dataset.borrow_mut();
+    pub fn optimize_indexes(&self, options: RHash) -> Result<(), Error> {
+        self.check_writable()?;
+        let mut dataset = self.dataset_mut()?;
         let dataset = dataset.as_mut()
             .ok_or_else(|| Error::new(magnus::exception::runtime_error(), "Dataset not opened"))?;
 
-        self.runtime.borrow_mut().block_on(async move {
-            // Create inverted index for full-text search
-            let params = InvertedIndexParams::default();
-            
-            dataset.create_index(
-                &[&column],
-                IndexType::Inverted,
+        let params = build_optimize_options(&options)?;
+
+        self.runtime()?.block_on(async move {
+            dataset.optimize_indices(&params).await
+        }).map_err(|e| Error::new(magnus::exception::runtime_error(), e.to_string()))
+    }
+
+    pub fn drop_index(&self, name: String) -> Result<(), Error> {
+        self.check_writable()?;
+        let mut dataset = self.dataset_mut()?;
+        let dataset = dataset.as_mut()
+            .ok_or_else(|| Error::new(magnus::exception::runtime_error(), "Dataset not opened"))?;
+
+        self.runtime()?.block_on(async move {
+            dataset.drop_index(&name).await
+        }).map_err(|e| Error::new(magnus::exception::runtime_error(), e.to_string()))
+    }
+
+    /// Lance has no rename operation, so the index metadata is committed again
+    /// under the new name, pointing at the same index files
+    pub fn rename_index(&self, from: String, to: String) -> Result<(), Error> {
+        self.check_writable()?;
+        let mut dataset = self.dataset_mut()?;
+        let dataset = dataset.as_mut()
+            .ok_or_else(|| Error::new(magnus::exception::runtime_error(), "Dataset not opened"))?;
+
+        self.runtime()?.block_on(async move {
+            let indices = dataset.load_indices()
+                .await
+                .map_err(|e| Error::new(magnus::exception::runtime_error(), e.to_string()))?;
+            if indices.iter().any(|index| index.name == to) {
+                return Err(Error::new(magnus::exception::arg_error(), format!("Index {} already exists", to)));
+            }
+
+            let removed: Vec<Index> = indices.iter().filter(|index| index.name == from).cloned().collect();
+            if removed.is_empty() {
+                return Err(Error::new(magnus::exception::arg_error(), format!("Index {} not found", from)));
+            }
+            let renamed = removed.iter()
+                .map(|index| Index { name: to.clone(), ..index.clone() })
+                .collect();
+
+            let transaction = Transaction::new(
+                dataset.manifest().version,
+                Operation::CreateIndex { new_indices: renamed, removed_indices: removed },
                 None,
-                &params,
-                true
-            )
-            .await
-            .map_err(|e| Error::new(magnus::exception::runtime_error(), e.to_string()))
+                None,
+            );
+            dataset.apply_commit(transaction, &Default::default(), &Default::default())
+                .await
+                .map_err(|e| Error::new(magnus::exception::runtime_error(), e.to_string()))
         })
     }
 
-    pub fn text_search(&self, column: String, query: String, limit: i64) -> Result<RArray, Error> {
-        let dataset = self.dataset.borrow();
+    pub fn text_search(&self, column: String, query: String, limit: i64, options: Option<RHash>) -> Result<RArray, Error> {
+        let dataset = self.dataset_ref()?;
         let dataset = dataset.as_ref()
             .ok_or_else(|| Error::new(magnus::exception::runtime_error(), "Dataset not opened"))?;
 
-        let batches: Vec<RecordBatch> = self.runtime.borrow_mut().block_on(async {
+        let snapshot = self.as_of_snapshot(dataset, &options)?;
+        let dataset = snapshot.as_ref().unwrap_or(dataset);
+
+        let arrow_schema: ArrowSchema = dataset.schema().into();
+
+        let offset = offset_option(options.as_ref())?;
+
+        let batches: Vec<RecordBatch> = self.runtime()?.block_on(async {
             let mut scanner = dataset.scan();
             
             // Use full-text search with inverted index
@@ -358,8 +1165,13 @@ impl LancelotDataset {
             scanner.full_text_search(fts_query)
                 .map_err(|e| Error::new(magnus::exception::runtime_error(), e.to_string()))?;
             
+            if let Some(options) = &options {
+                apply_filter_options(&mut scanner, &arrow_schema, options)?;
+                apply_projection(&mut scanner, &arrow_schema, options)?;
+            }
+            
             // Apply limit
-            scanner.limit(Some(limit), None)
+            scanner.limit(Some(limit), offset)
                 .map_err(|e| Error::new(magnus::exception::runtime_error(), e.to_string()))?;
             
             let stream = scanner
@@ -387,18 +1199,25 @@ impl LancelotDataset {
         Ok(result_array)
     }
 
-    pub fn multi_column_text_search(&self, columns: RArray, query: String, limit: i64) -> Result<RArray, Error> {
-        let dataset = self.dataset.borrow();
+    pub fn multi_column_text_search(&self, columns: RArray, query: String, limit: i64, options: Option<RHash>) -> Result<RArray, Error> {
+        let dataset = self.dataset_ref()?;
         let dataset = dataset.as_ref()
             .ok_or_else(|| Error::new(magnus::exception::runtime_error(), "Dataset not opened"))?;
 
+        let snapshot = self.as_of_snapshot(dataset, &options)?;
+        let dataset = snapshot.as_ref().unwrap_or(dataset);
+
+        let arrow_schema: ArrowSchema = dataset.schema().into();
+
         // Convert Ruby array of columns to Vec<String>
         let columns: Vec<String> = columns
             .into_iter()
             .map(|v| String::try_convert(v))
             .collect::<Result<Vec<_>, _>>()?;
 
-        let batches: Vec<RecordBatch> = self.runtime.borrow_mut().block_on(async {
+        let offset = offset_option(options.as_ref())?;
+
+        let batches: Vec<RecordBatch> = self.runtime()?.block_on(async {
             let mut scanner = dataset.scan();
             
             // Create a full-text search query for multiple columns
@@ -409,8 +1228,13 @@ impl LancelotDataset {
             scanner.full_text_search(fts_query)
                 .map_err(|e| Error::new(magnus::exception::runtime_error(), e.to_string()))?;
             
+            if let Some(options) = &options {
+                apply_filter_options(&mut scanner, &arrow_schema, options)?;
+                apply_projection(&mut scanner, &arrow_schema, options)?;
+            }
+            
             // Apply limit
-            scanner.limit(Some(limit), None)
+            scanner.limit(Some(limit), offset)
                 .map_err(|e| Error::new(magnus::exception::runtime_error(), e.to_string()))?;
             
             let stream = scanner
@@ -438,24 +1262,34 @@ impl LancelotDataset {
         Ok(result_array)
     }
 
-    pub fn filter_scan(&self, filter_expr: String, limit: Option<i64>) -> Result<RArray, Error> {
-        let dataset = self.dataset.borrow();
+    /// Full-text search with a structured query built by `Lancelot::Query`
+    pub fn query_text_search(&self, query: RHash, limit: i64, options: Option<RHash>) -> Result<RArray, Error> {
+        let dataset = self.dataset_ref()?;
         let dataset = dataset.as_ref()
             .ok_or_else(|| Error::new(magnus::exception::runtime_error(), "Dataset not opened"))?;
 
-        let batches: Vec<RecordBatch> = self.runtime.borrow_mut().block_on(async {
+        let snapshot = self.as_of_snapshot(dataset, &options)?;
+        let dataset = snapshot.as_ref().unwrap_or(dataset);
+
+        let arrow_schema: ArrowSchema = dataset.schema().into();
+        let fts_query = FullTextSearchQuery::new_query(parse_fts_query(query)?);
+
+        let offset = offset_option(options.as_ref())?;
+
+        let batches: Vec<RecordBatch> = self.runtime()?.block_on(async {
             let mut scanner = dataset.scan();
             
-            // Apply SQL-like filter
-            scanner.filter(&filter_expr)
+            scanner.full_text_search(fts_query)
                 .map_err(|e| Error::new(magnus::exception::runtime_error(), e.to_string()))?;
             
-            // Apply limit if provided
-            if let Some(lim) = limit {
-                scanner.limit(Some(lim), None)
-                    .map_err(|e| Error::new(magnus::exception::runtime_error(), e.to_string()))?;
+            if let Some(options) = &options {
+                apply_filter_options(&mut scanner, &arrow_schema, options)?;
+                apply_projection(&mut scanner, &arrow_schema, options)?;
             }
             
+            scanner.limit(Some(limit), offset)
+                .map_err(|e| Error::new(magnus::exception::runtime_error(), e.to_string()))?;
+            
             let stream = scanner
                 .try_into_stream()
                 .await
@@ -472,7 +1306,6 @@ impl LancelotDataset {
 
         for batch in batches {
             let batch_docs = convert_batch_to_ruby(&batch)?;
-            // Merge arrays by pushing each element
             for i in 0..batch_docs.len() {
                 result_array.push(batch_docs.entry::<Value>(i as isize)?)?;
             }
@@ -480,25 +1313,431 @@ impl LancelotDataset {
 
         Ok(result_array)
     }
+
+    /// Vector and full-text search fused by row id. Returns the top `limit`
+    /// rows, each with its `_distance`, text `_score` and fused `_relevance_score`.
+    pub fn hybrid_search(&self, query_vector: RArray, text_query: Value, limit: i64, options: RHash) -> Result<RArray, Error> {
+        let dataset = self.dataset_ref()?;
+        let dataset = dataset.as_ref()
+            .ok_or_else(|| Error::new(magnus::exception::runtime_error(), "Dataset not opened"))?;
+
+        let snapshot = self.as_of_snapshot(dataset, &Some(options))?;
+        let dataset = snapshot.as_ref().unwrap_or(dataset);
+
+        let arrow_schema: ArrowSchema = dataset.schema().into();
+        let vector_column = get_opt_string(&options, "vector_column")?.unwrap_or_else(|| "vector".to_string());
+        if !matches!(arrow_schema.field_with_name(&vector_column).map(|f| f.data_type()), Ok(DataType::FixedSizeList(_, _))) {
+            return Err(Error::new(
+                magnus::exception::arg_error(),
+                format!("Column {} is not a vector column", vector_column)
+            ));
+        }
+        let metric = resolve_metric(Some(&options), &arrow_schema, &vector_column)?;
+        let query = query_vector_array(&arrow_schema, &vector_column, query_vector)?;
+        let query_values: Vec<f32> = query_vector
+            .into_iter()
+            .map(|v| f64::try_convert(v).map(|f| f as f32))
+            .collect::<Result<Vec<_>, _>>()?;
+
+        // Structured queries name their own columns
+        let fts_query = match RHash::from_value(text_query) {
+            Some(structured) => FullTextSearchQuery::new_query(parse_fts_query(structured)?),
+            None => {
+                let fts_query = FullTextSearchQuery::new(String::try_convert(text_query)?);
+                match get_opt::<Vec<String>>(&options, "text_columns")? {
+                    Some(columns) => fts_query.with_columns(&columns)
+                        .map_err(|e| Error::new(magnus::exception::runtime_error(), e.to_string()))?,
+                    None => fts_query,
+                }
+            }
+        };
+
+        let fusion = parse_fusion(&options)?;
+        let vector_weight: f32 = get_opt(&options, "vector_weight")?.unwrap_or(1.0);
+        let text_weight: f32 = get_opt(&options, "text_weight")?.unwrap_or(1.0);
+        let limit = limit as usize;
+        let candidates = get_opt::<usize>(&options, "candidates")?.unwrap_or(limit * 2);
+        let columns = projected_columns(&arrow_schema, &options)?
+            .unwrap_or_else(|| arrow_schema.fields().iter().map(|f| f.name().clone()).collect());
+        let with_row_id = get_opt::<bool>(&options, "with_row_id")?.unwrap_or(false);
+
+        let (rows, batch) = self.runtime()?.block_on(async {
+            let mut vector_scanner = dataset.scan();
+            vector_scanner.nearest(&vector_column, query.as_ref(), candidates)
+                .map_err(|e| Error::new(magnus::exception::runtime_error(), e.to_string()))?;
+            if let Some(metric) = metric {
+                vector_scanner.distance_metric(metric);
+            }
+            apply_ann_options(&mut vector_scanner, &options)?;
+            apply_filter_options(&mut vector_scanner, &arrow_schema, &options)?;
+            let vector_hits = search_hits(vector_scanner, "_distance").await
+                .map_err(|e| Error::new(magnus::exception::runtime_error(), e.to_string()))?;
+
+            let mut text_scanner = dataset.scan();
+            text_scanner.full_text_search(fts_query)
+                .map_err(|e| Error::new(magnus::exception::runtime_error(), e.to_string()))?;
+            apply_filter_options(&mut text_scanner, &arrow_schema, &options)?;
+            text_scanner.limit(Some(candidates as i64), None)
+                .map_err(|e| Error::new(magnus::exception::runtime_error(), e.to_string()))?;
+            let text_hits = search_hits(text_scanner, "_score").await
+                .map_err(|e| Error::new(magnus::exception::runtime_error(), e.to_string()))?;
+
+            let mut rows = collect_candidates(&vector_hits, &text_hits);
+            if let Fusion::Distance = fusion {
+                fill_distances(dataset, &mut rows, &vector_column, metric.unwrap_or(MetricType::L2), &query_values).await
+                    .map_err(|e| Error::new(magnus::exception::runtime_error(), e.to_string()))?;
+            }
+            let rows = rank(rows, &fusion, vector_weight, text_weight, limit);
+
+            // Read full rows for the final top-k only
+            let row_ids: Vec<u64> = rows.iter().map(|row| row.row_id).collect();
+            let batch = take_in_order(dataset, &row_ids, &columns).await
+                .map_err(|e| Error::new(magnus::exception::runtime_error(), e.to_string()))?;
+
+            Ok::<_, Error>((rows, batch))
+        })?;
+
+        let documents = convert_batch_to_ruby(&batch)?;
+        for (i, row) in rows.iter().enumerate() {
+            let doc = RHash::try_convert(documents.entry::<Value>(i as isize)?)?;
+            doc.aset(Symbol::new("_distance"), row.distance)?;
+            doc.aset(Symbol::new("_score"), row.text_score)?;
+            doc.aset(Symbol::new("_relevance_score"), row.score)?;
+            if with_row_id {
+                doc.aset(Symbol::new("_rowid"), row.row_id)?;
+            }
+            if let Fusion::Rrf { .. } = fusion {
+                doc.aset(Symbol::new("rrf_score"), row.score)?;
+            }
+        }
+
+        Ok(documents)
+    }
+
+    /// Run a read-only SQL query, with the dataset registered as the table
+    /// `self` and any datasets in the `tables` option under their names
+    pub fn sql(&self, query: String, params: Value, options: RHash) -> Result<RArray, Error> {
+        let dataset = self.dataset_ref()?;
+        let dataset = dataset.as_ref()
+            .ok_or_else(|| Error::new(magnus::exception::runtime_error(), "Dataset not opened"))?;
+
+        let snapshot = self.as_of_snapshot(dataset, &Some(options))?;
+        let dataset = snapshot.as_ref().unwrap_or(dataset);
+
+        let mut tables: Vec<(String, Dataset)> = vec![(SELF_TABLE.to_string(), dataset.clone())];
+        if let Some(others) = get_opt::<RHash>(&options, "tables")? {
+            others.foreach(|name: Value, other: &LancelotDataset| {
+                let other_dataset = other.dataset_ref()?.clone()
+                    .ok_or_else(|| Error::new(magnus::exception::runtime_error(), "Dataset not opened"))?;
+                tables.push((value_to_string(name)?, other_dataset));
+                Ok(ForEach::Continue)
+            })?;
+        }
+
+        let (query, values) = match parse_params(params)? {
+            Some(params) => number_placeholders(&query, params, dollar_placeholder)?,
+            None => (query, Vec::new()),
+        };
+
+        let batches: Vec<RecordBatch> = self.runtime()?.block_on(async {
+            let context = SessionContext::new();
+            for (name, table) in tables {
+                context.register_table(name.as_str(), Arc::new(LanceTableProvider::new(Arc::new(table), false, false)))?;
+            }
+
+            // Queries can read the registered tables but not create or change any
+            let sql_options = SQLOptions::new()
+                .with_allow_ddl(false)
+                .with_allow_dml(false)
+                .with_allow_statements(false);
+            let mut frame = context.sql_with_options(&query, sql_options).await?;
+            if !values.is_empty() {
+                frame = frame.with_param_values(ParamValues::List(values))?;
+            }
+            frame.collect().await
+        }).map_err(|e| Error::new(magnus::exception::runtime_error(), e.to_string()))?;
+
+        let ruby = Ruby::get().unwrap();
+        let result_array = ruby.ary_new();
+
+        for batch in batches {
+            let batch_docs = convert_batch_to_ruby(&batch)?;
+            for i in 0..batch_docs.len() {
+                result_array.push(batch_docs.entry::<Value>(i as isize)?)?;
+            }
+        }
+
+        Ok(result_array)
+    }
+
+    /// Rows by row id, in the order given
+    pub fn take_rows(&self, row_ids: Vec<u64>, options: RHash) -> Result<RArray, Error> {
+        self.take_indices(row_ids, true, options)
+    }
+
+    /// Rows by position in the dataset, in the order given
+    pub fn take(&self, offsets: Vec<u64>, options: RHash) -> Result<RArray, Error> {
+        self.take_indices(offsets, false, options)
+    }
+
+    fn take_indices(&self, indices: Vec<u64>, by_row_id: bool, options: RHash) -> Result<RArray, Error> {
+        let dataset = self.dataset_ref()?;
+        let dataset = dataset.as_ref()
+            .ok_or_else(|| Error::new(magnus::exception::runtime_error(), "Dataset not opened"))?;
+
+        let snapshot = self.as_of_snapshot(dataset, &Some(options))?;
+        let dataset = snapshot.as_ref().unwrap_or(dataset);
+
+        let arrow_schema: ArrowSchema = dataset.schema().into();
+        let columns = projected_columns(&arrow_schema, &options)?
+            .unwrap_or_else(|| arrow_schema.fields().iter().map(|f| f.name().clone()).collect());
+        let projection = ProjectionRequest::from_columns(&columns, dataset.schema());
+
+        let batch = self.runtime()?.block_on(async {
+            if by_row_id {
+                dataset.take_rows(&indices, projection).await
+            } else {
+                dataset.take(&indices, projection).await
+            }
+        }).map_err(|e| Error::new(magnus::exception::runtime_error(), e.to_string()))?;
+
+        convert_batch_to_ruby(&batch)
+    }
+
+    pub fn filter_scan(&self, filter_expr: String, limit: Option<i64>, options: Option<RHash>) -> Result<Value, Error> {
+        let dataset = self.dataset_ref()?;
+        let dataset = dataset.as_ref()
+            .ok_or_else(|| Error::new(magnus::exception::runtime_error(), "Dataset not opened"))?;
+
+        let snapshot = self.as_of_snapshot(dataset, &options)?;
+        let dataset = snapshot.as_ref().unwrap_or(dataset);
+
+        let arrow_schema: ArrowSchema = dataset.schema().into();
+
+        let offset = offset_option(options.as_ref())?;
+
+        let output = self.runtime()?.block_on(async {
+            let mut scanner = dataset.scan();
+            
+            // Apply SQL-like filter, with any bound parameters
+            let params = match &options {
+                Some(options) => filter_params(options)?,
+                None => None,
+            };
+            apply_filter(&mut scanner, &arrow_schema, &filter_expr, params)?;
+            
+            if let Some(options) = &options {
+                apply_projection(&mut scanner, &arrow_schema, options)?;
+                apply_order_by(&mut scanner, &arrow_schema, options)?;
+            }
+            
+            // Apply limit and offset if provided
+            if limit.is_some() || offset.is_some() {
+                scanner.limit(limit, offset)
+                    .map_err(|e| Error::new(magnus::exception::runtime_error(), e.to_string()))?;
+            }
+            
+            execute(&scanner, options.as_ref()).await
+        })?;
+
+        scan_output_to_ruby(output)
+    }
+
+    pub fn add_columns(&self, expressions: RHash) -> Result<(), Error> {
+        self.check_writable()?;
+        let mut dataset = self.dataset_mut()?;
+        let dataset = dataset.as_mut()
+            .ok_or_else(|| Error::new(magnus::exception::runtime_error(), "Dataset not opened"))?;
+
+        // Each new column is computed from a SQL expression over existing columns
+        let mut columns = Vec::new();
+        expressions.foreach(|name: Symbol, expression: String| {
+            columns.push((name.name()?.to_string(), expression));
+            Ok(ForEach::Continue)
+        })?;
+
+        self.runtime()?.block_on(async move {
+            dataset.add_columns(NewColumnTransform::SqlExpressions(columns), None, None)
+                .await
+                .map_err(|e| Error::new(magnus::exception::runtime_error(), e.to_string()))
+        })
+    }
+
+    pub fn add_null_columns(&self, schema_hash: RHash) -> Result<(), Error> {
+        self.check_writable()?;
+        let mut dataset = self.dataset_mut()?;
+        let dataset = dataset.as_mut()
+            .ok_or_else(|| Error::new(magnus::exception::runtime_error(), "Dataset not opened"))?;
+
+        let schema = build_arrow_schema(schema_hash)?;
+
+        // All-null columns only touch the manifest, no data files are written
+        self.runtime()?.block_on(async move {
+            dataset.add_columns(NewColumnTransform::AllNulls(Arc::new(schema)), None, None)
+                .await
+                .map_err(|e| Error::new(magnus::exception::runtime_error(), e.to_string()))
+        })
+    }
+
+    pub fn drop_columns(&self, columns: RArray) -> Result<(), Error> {
+        self.check_writable()?;
+        let mut dataset = self.dataset_mut()?;
+        let dataset = dataset.as_mut()
+            .ok_or_else(|| Error::new(magnus::exception::runtime_error(), "Dataset not opened"))?;
+
+        let columns: Vec<String> = columns
+            .into_iter()
+            .map(|v| String::try_convert(v))
+            .collect::<Result<Vec<_>, _>>()?;
+        let columns: Vec<&str> = columns.iter().map(|c| c.as_str()).collect();
+
+        self.runtime()?.block_on(async move {
+            dataset.drop_columns(&columns)
+                .await
+                .map_err(|e| Error::new(magnus::exception::runtime_error(), e.to_string()))
+        })
+    }
+
+    pub fn alter_columns(&self, alterations: RHash) -> Result<(), Error> {
+        self.check_writable()?;
+        let mut dataset = self.dataset_mut()?;
+        let dataset = dataset.as_mut()
+            .ok_or_else(|| Error::new(magnus::exception::runtime_error(), "Dataset not opened"))?;
+
+        let mut column_alterations = Vec::new();
+        alterations.foreach(|column: Symbol, options: RHash| {
+            let mut alteration = ColumnAlteration::new(column.name()?.to_string());
+
+            if let Some(rename) = get_opt_string(&options, "rename")? {
+                alteration = alteration.rename(rename);
+            }
+            if let Some(nullable) = get_opt::<bool>(&options, "nullable")? {
+                alteration = alteration.set_nullable(nullable);
+            }
+            if let Some(cast) = get_opt::<Value>(&options, "cast")? {
+                let (data_type, _) = parse_field_type(cast)?;
+                alteration = alteration.cast_to(data_type);
+            }
+
+            column_alterations.push(alteration);
+            Ok(ForEach::Continue)
+        })?;
+
+        self.runtime()?.block_on(async move {
+            dataset.alter_columns(&column_alterations)
+                .await
+                .map_err(|e| Error::new(magnus::exception::runtime_error(), e.to_string()))
+        })
+    }
+
+    pub fn add_column_with(&self, name: String, field_type: Value, options: RHash, block: Proc) -> Result<(), Error> {
+        self.check_writable()?;
+        let mut dataset = self.dataset_mut()?;
+        let dataset = dataset.as_mut()
+            .ok_or_else(|| Error::new(magnus::exception::runtime_error(), "Dataset not opened"))?;
+
+        let (data_type, metadata) = parse_field_type(field_type)?;
+        let read_columns: Option<Vec<String>> = get_opt(&options, "columns")?;
+        let batch_size: Option<u32> = get_opt(&options, "batch_size")?;
+
+        // Batches are keyed by fragment and index, so they are only reusable
+        // by a backfill over the same version, column and batching
+        let manifest = CheckpointManifest {
+            version: dataset.version().version,
+            column: name.clone(),
+            data_type: data_type.to_string(),
+            read_columns: read_columns.clone(),
+            batch_size,
+        };
+        let output_schema = Arc::new(ArrowSchema::new(vec![
+            Field::new(&name, data_type, true).with_metadata(metadata)
+        ]));
+
+        let checkpoint = get_opt_string(&options, "checkpoint")?
+            .map(|dir| FileCheckpointStore::new(dir, &manifest).map(Arc::new))
+            .transpose()
+            .map_err(|e| Error::new(magnus::exception::runtime_error(), e.to_string()))?;
+
+        // Lance drives the mapper from the calling thread, so the block runs with the GVL held
+        let block = Opaque::from(block);
+        let mapper_schema = output_schema.clone();
+        let mapper = move |batch: &RecordBatch| -> lance::Result<RecordBatch> {
+            let ruby = Ruby::get()
+                .map_err(|e| ArrowError::ExternalError(e.to_string().into()))?;
+            compute_column_batch(&ruby, ruby.get_inner(block), batch, &mapper_schema)
+                .map_err(|e| ArrowError::ExternalError(e.to_string().into()).into())
+        };
+
+        let transform = NewColumnTransform::BatchUDF(BatchUDF {
+            mapper: Box::new(mapper),
+            output_schema,
+            result_checkpoint: checkpoint.clone().map(|store| store as Arc<dyn UDFCheckpointStore>),
+        });
+
+        self.runtime()?.block_on(async move {
+            dataset.add_columns(transform, read_columns, batch_size)
+                .await
+                .map_err(|e| Error::new(magnus::exception::runtime_error(), e.to_string()))
+        })?;
+
+        // The column is committed, so the intermediate results are no longer needed
+        if let Some(checkpoint) = checkpoint {
+            checkpoint.remove()
+                .map_err(|e| Error::new(magnus::exception::runtime_error(), e.to_string()))?;
+        }
+
+        Ok(())
+    }
 }
 
 impl LancelotDataset {
     pub fn bind(class: &RClass) -> Result<(), Error> {
         class.define_singleton_method("new", function!(LancelotDataset::new, 1))?;
         class.define_method("path", method!(LancelotDataset::path, 0))?;
-        class.define_method("create", method!(LancelotDataset::create, 1))?;
-        class.define_method("open", method!(LancelotDataset::open, 0))?;
-        class.define_method("add_data", method!(LancelotDataset::add_data, 1))?;
+        class.define_method("create", method!(LancelotDataset::create, 2))?;
+        class.define_method("open", method!(LancelotDataset::open, 1))?;
+        class.define_method("read_only?", method!(LancelotDataset::is_read_only, 0))?;
+        class.define_method("version", method!(LancelotDataset::version, 0))?;
+        class.define_method("_rust_versions", method!(LancelotDataset::versions, 0))?;
+        class.define_method("_rust_fragments", method!(LancelotDataset::fragments, 0))?;
+        class.define_method("checkout", method!(LancelotDataset::checkout, 1))?;
+        class.define_method("restore", method!(LancelotDataset::restore, 1))?;
+        class.define_method("_rust_diff", method!(LancelotDataset::diff, 2))?;
+        class.define_method("_rust_cleanup", method!(LancelotDataset::cleanup, 2))?;
+        class.define_method("_rust_cleanup_plan", method!(LancelotDataset::cleanup_plan, 2))?;
+        class.define_method("_rust_compact", method!(LancelotDataset::compact, 1))?;
+        class.define_method("_rust_create_tag", method!(LancelotDataset::create_tag, 2))?;
+        class.define_method("_rust_delete_tag", method!(LancelotDataset::delete_tag, 1))?;
+        class.define_method("_rust_tags", method!(LancelotDataset::tags, 0))?;
+        class.define_method("add_data", method!(LancelotDataset::add_data, 2))?;
         class.define_method("count_rows", method!(LancelotDataset::count_rows, 0))?;
         class.define_method("schema", method!(LancelotDataset::schema, 0))?;
-        class.define_method("scan_all", method!(LancelotDataset::scan_all, 0))?;
-        class.define_method("scan_limit", method!(LancelotDataset::scan_limit, 1))?;
-        class.define_method("create_vector_index", method!(LancelotDataset::create_vector_index, 1))?;
-        class.define_method("create_text_index", method!(LancelotDataset::create_text_index, 1))?;
-        class.define_method("_rust_vector_search", method!(LancelotDataset::vector_search, 3))?;
-        class.define_method("_rust_text_search", method!(LancelotDataset::text_search, 3))?;
-        class.define_method("_rust_multi_column_text_search", method!(LancelotDataset::multi_column_text_search, 3))?;
-        class.define_method("filter_scan", method!(LancelotDataset::filter_scan, 2))?;
+        class.define_method("_rust_scan_all", method!(LancelotDataset::scan_all, 1))?;
+        class.define_method("_rust_scan_limit", method!(LancelotDataset::scan_limit, 2))?;
+        class.define_method("_rust_create_vector_index", method!(LancelotDataset::create_vector_index, 2))?;
+        class.define_method("_rust_create_scalar_index", method!(LancelotDataset::create_scalar_index, 2))?;
+        class.define_method("_rust_create_text_index", method!(LancelotDataset::create_text_index, 2))?;
+        class.define_method("_rust_indexes", method!(LancelotDataset::indexes, 0))?;
+        class.define_method("_rust_index_stats", method!(LancelotDataset::index_stats, 1))?;
+        class.define_method("_rust_drop_index", method!(LancelotDataset::drop_index, 1))?;
+        class.define_method("_rust_rename_index", method!(LancelotDataset::rename_index, 2))?;
+        class.define_method("_rust_optimize_indexes", method!(LancelotDataset::optimize_indexes, 1))?;
+        class.define_method("auto_optimize_indexes", method!(LancelotDataset::auto_optimize_indexes, 0))?;
+        class.define_method("_rust_set_auto_optimize_indexes", method!(LancelotDataset::set_auto_optimize_indexes, 1))?;
+        class.define_method("_rust_vector_search", method!(LancelotDataset::vector_search, 4))?;
+        class.define_method("_rust_text_search", method!(LancelotDataset::text_search, 4))?;
+        class.define_method("_rust_multi_column_text_search", method!(LancelotDataset::multi_column_text_search, 4))?;
+        class.define_method("_rust_query_text_search", method!(LancelotDataset::query_text_search, 3))?;
+        class.define_method("_rust_hybrid_search", method!(LancelotDataset::hybrid_search, 4))?;
+        class.define_method("_rust_sql", method!(LancelotDataset::sql, 3))?;
+        class.define_method("_rust_take_rows", method!(LancelotDataset::take_rows, 2))?;
+        class.define_method("_rust_take", method!(LancelotDataset::take, 2))?;
+        class.define_method("_rust_filter_scan", method!(LancelotDataset::filter_scan, 3))?;
+        class.define_method("_rust_add_columns", method!(LancelotDataset::add_columns, 1))?;
+        class.define_method("_rust_add_null_columns", method!(LancelotDataset::add_null_columns, 1))?;
+        class.define_method("_rust_drop_columns", method!(LancelotDataset::drop_columns, 1))?;
+        class.define_method("_rust_alter_columns", method!(LancelotDataset::alter_columns, 1))?;
+        class.define_method("_rust_add_column_with", method!(LancelotDataset::add_column_with, 4))?;
         Ok(())
     }
 }
\ No newline at end of file
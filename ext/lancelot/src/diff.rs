@@ -0,0 +1,109 @@
+use arrow::compute::filter_record_batch;
+use arrow_array::{Array, BooleanArray, RecordBatch, UInt64Array};
+use arrow_schema::ArrowError;
+use futures::stream::TryStreamExt;
+use lance::dataset::fragment::FileFragment;
+use lance::Dataset;
+use lance_core::utils::deletion::DeletionVector;
+use lance_table::format::Fragment;
+use std::collections::HashMap;
+
+/// Rows that disappeared from and appeared in a dataset between two versions.
+///
+/// An updated row shows up in both lists, since Lance updates rows by
+/// deleting them and appending the new values; callers pair them up by key.
+#[derive(Default)]
+pub struct VersionDiff {
+    pub removed: Vec<RecordBatch>,
+    pub added: Vec<RecordBatch>,
+}
+
+/// Fragments with the same id and data files hold the same physical rows
+fn same_data_files(a: &Fragment, b: &Fragment) -> bool {
+    a.files.len() == b.files.len()
+        && a.files.iter().zip(b.files.iter()).all(|(x, y)| x.path == y.path)
+}
+
+/// Compare two versions using fragment metadata, reading only fragments that changed
+pub async fn diff_versions(from: &Dataset, to: &Dataset) -> lance::Result<VersionDiff> {
+    let from_fragments: HashMap<usize, FileFragment> = from.get_fragments()
+        .into_iter()
+        .map(|fragment| (fragment.id(), fragment))
+        .collect();
+    let to_fragments: HashMap<usize, FileFragment> = to.get_fragments()
+        .into_iter()
+        .map(|fragment| (fragment.id(), fragment))
+        .collect();
+
+    let mut diff = VersionDiff::default();
+
+    for (id, from_fragment) in &from_fragments {
+        match to_fragments.get(id) {
+            Some(to_fragment) if same_data_files(from_fragment.metadata(), to_fragment.metadata()) => {
+                // Same rows on disk, so only a changed deletion file can make a difference
+                if from_fragment.metadata().deletion_file != to_fragment.metadata().deletion_file {
+                    diff.removed.extend(scan_rows_deleted_in(from, from_fragment, to_fragment).await?);
+                    diff.added.extend(scan_rows_deleted_in(to, to_fragment, from_fragment).await?);
+                }
+            }
+            _ => diff.removed.extend(scan_fragment(from, from_fragment.metadata(), None).await?),
+        }
+    }
+
+    for (id, to_fragment) in &to_fragments {
+        let unchanged = from_fragments.get(id)
+            .is_some_and(|from_fragment| same_data_files(from_fragment.metadata(), to_fragment.metadata()));
+        if !unchanged {
+            diff.added.extend(scan_fragment(to, to_fragment.metadata(), None).await?);
+        }
+    }
+
+    Ok(diff)
+}
+
+/// Live rows of `fragment` that are deleted in the other version of the same fragment
+async fn scan_rows_deleted_in(
+    dataset: &Dataset,
+    fragment: &FileFragment,
+    other: &FileFragment,
+) -> lance::Result<Vec<RecordBatch>> {
+    match other.get_deletion_vector().await? {
+        Some(deletions) => scan_fragment(dataset, fragment.metadata(), Some(&deletions)).await,
+        None => Ok(Vec::new()),
+    }
+}
+
+/// Read the live rows of a fragment, optionally keeping only offsets in `only`
+async fn scan_fragment(
+    dataset: &Dataset,
+    fragment: &Fragment,
+    only: Option<&DeletionVector>,
+) -> lance::Result<Vec<RecordBatch>> {
+    let mut scanner = dataset.scan();
+    scanner.with_fragments(vec![fragment.clone()]).with_row_address();
+
+    let batches: Vec<RecordBatch> = scanner.try_into_stream().await?.try_collect().await?;
+
+    batches.into_iter()
+        .map(|batch| select_rows(batch, only))
+        .collect()
+}
+
+fn select_rows(mut batch: RecordBatch, only: Option<&DeletionVector>) -> lance::Result<RecordBatch> {
+    let address_index = batch.schema().index_of("_rowaddr")?;
+    let addresses = batch.remove_column(address_index);
+
+    let Some(only) = only else {
+        return Ok(batch);
+    };
+
+    // The lower 32 bits of a row address are the row's offset within its fragment
+    let addresses = addresses.as_any().downcast_ref::<UInt64Array>()
+        .ok_or_else(|| ArrowError::SchemaError("_rowaddr is not a UInt64 column".into()))?;
+    let mask: BooleanArray = addresses.values()
+        .iter()
+        .map(|address| Some(only.contains(*address as u32)))
+        .collect();
+
+    Ok(filter_record_batch(&batch, &mask)?)
+}
@@ -0,0 +1,287 @@
+use magnus::{Error, RHash};
+use arrow::compute::take_record_batch;
+use arrow_array::{Array, ArrayRef, FixedSizeListArray, Float16Array, Float32Array, RecordBatch, UInt8Array, UInt32Array, UInt64Array};
+use arrow_schema::ArrowError;
+use futures::stream::TryStreamExt;
+use lance::dataset::scanner::Scanner;
+use lance::dataset::ProjectionRequest;
+use lance::Dataset;
+use lance_linalg::distance::MetricType;
+use std::collections::HashMap;
+
+use crate::options::{get_opt, get_opt_string};
+
+/// How vector and full-text results are combined into one ranking
+pub enum Fusion {
+    /// Weighted reciprocal rank fusion, `Σ weight / (k + rank)`
+    Rrf { k: f32 },
+    /// Weighted sum of the normalised vector similarity and text score
+    Linear { normalization: Normalization },
+    /// Candidates from both searches, ordered by vector distance alone. The
+    /// score is the negated distance, since dot distances can be negative.
+    Distance,
+}
+
+pub enum Normalization {
+    MinMax,
+    ZScore,
+}
+
+/// A candidate row with the scores it got from each search
+pub struct FusedRow {
+    pub row_id: u64,
+    pub vector_rank: Option<usize>,
+    pub distance: Option<f32>,
+    pub text_rank: Option<usize>,
+    pub text_score: Option<f32>,
+    pub score: f32,
+}
+
+pub fn parse_fusion(options: &RHash) -> Result<Fusion, Error> {
+    match get_opt_string(options, "fusion")?.as_deref() {
+        None | Some("rrf") => Ok(Fusion::Rrf { k: get_opt(options, "rrf_k")?.unwrap_or(60.0) }),
+        Some("linear") => {
+            let normalization = match get_opt_string(options, "normalization")?.as_deref() {
+                None | Some("minmax") => Normalization::MinMax,
+                Some("zscore") => Normalization::ZScore,
+                Some(other) => return Err(Error::new(
+                    magnus::exception::arg_error(),
+                    format!("Unknown normalization: {}", other)
+                ))
+            };
+            Ok(Fusion::Linear { normalization })
+        }
+        Some("distance") => Ok(Fusion::Distance),
+        Some(other) => Err(Error::new(
+            magnus::exception::arg_error(),
+            format!("Unknown fusion: {}", other)
+        ))
+    }
+}
+
+/// Merge vector hits (row id and distance, nearest first) with text hits
+/// (row id and score, best first) into one candidate per row
+pub fn collect_candidates(vector_hits: &[(u64, f32)], text_hits: &[(u64, f32)]) -> Vec<FusedRow> {
+    let mut rows: Vec<FusedRow> = Vec::with_capacity(vector_hits.len() + text_hits.len());
+    let mut positions: HashMap<u64, usize> = HashMap::new();
+
+    for (rank, (row_id, distance)) in vector_hits.iter().enumerate() {
+        positions.insert(*row_id, rows.len());
+        rows.push(FusedRow {
+            row_id: *row_id,
+            vector_rank: Some(rank + 1),
+            distance: Some(*distance),
+            text_rank: None,
+            text_score: None,
+            score: 0.0,
+        });
+    }
+
+    for (rank, (row_id, text_score)) in text_hits.iter().enumerate() {
+        match positions.get(row_id) {
+            Some(&position) => {
+                rows[position].text_rank = Some(rank + 1);
+                rows[position].text_score = Some(*text_score);
+            }
+            None => {
+                positions.insert(*row_id, rows.len());
+                rows.push(FusedRow {
+                    row_id: *row_id,
+                    vector_rank: None,
+                    distance: None,
+                    text_rank: Some(rank + 1),
+                    text_score: Some(*text_score),
+                    score: 0.0,
+                });
+            }
+        }
+    }
+
+    rows
+}
+
+/// Score the candidates and return the best `limit`, highest score first.
+///
+/// Distance fusion needs every candidate's distance, so the caller fills in
+/// distances for rows only the text search found.
+pub fn rank(mut rows: Vec<FusedRow>, fusion: &Fusion, vector_weight: f32, text_weight: f32, limit: usize) -> Vec<FusedRow> {
+    match fusion {
+        Fusion::Rrf { k } => {
+            for row in rows.iter_mut() {
+                let vector = row.vector_rank.map_or(0.0, |rank| vector_weight / (k + rank as f32));
+                let text = row.text_rank.map_or(0.0, |rank| text_weight / (k + rank as f32));
+                row.score = vector + text;
+            }
+        }
+        Fusion::Linear { normalization } => {
+            // Lower distances are better, so negate them to get a similarity
+            let similarities = normalize(rows.iter().map(|row| row.distance.map(|d| -d)), normalization);
+            let text_scores = normalize(rows.iter().map(|row| row.text_score), normalization);
+            for (row, (similarity, text_score)) in rows.iter_mut().zip(similarities.into_iter().zip(text_scores)) {
+                row.score = vector_weight * similarity + text_weight * text_score;
+            }
+        }
+        Fusion::Distance => {
+            // Lower distances are better for every metric, so negate them as
+            // linear fusion does; rows without a vector rank last
+            for row in rows.iter_mut() {
+                row.score = row.distance.map_or(f32::NEG_INFINITY, |d| -d);
+            }
+        }
+    }
+
+    rows.sort_by(|a, b| b.score.total_cmp(&a.score));
+    rows.truncate(limit);
+    rows
+}
+
+/// Normalise one search's scores. Rows the search did not return get its
+/// lowest normalised score.
+fn normalize(scores: impl Iterator<Item = Option<f32>>, normalization: &Normalization) -> Vec<f32> {
+    let scores: Vec<Option<f32>> = scores.collect();
+    let present: Vec<f32> = scores.iter().flatten().copied().collect();
+    if present.is_empty() {
+        return vec![0.0; scores.len()];
+    }
+
+    let normalized: Vec<Option<f32>> = match normalization {
+        Normalization::MinMax => {
+            let min = present.iter().copied().fold(f32::INFINITY, f32::min);
+            let max = present.iter().copied().fold(f32::NEG_INFINITY, f32::max);
+            scores.iter()
+                .map(|score| score.map(|s| if max > min { (s - min) / (max - min) } else { 1.0 }))
+                .collect()
+        }
+        Normalization::ZScore => {
+            let mean = present.iter().sum::<f32>() / present.len() as f32;
+            let variance = present.iter().map(|s| (s - mean).powi(2)).sum::<f32>() / present.len() as f32;
+            let std_dev = variance.sqrt();
+            scores.iter()
+                .map(|score| score.map(|s| if std_dev > 0.0 { (s - mean) / std_dev } else { 0.0 }))
+                .collect()
+        }
+    };
+
+    let lowest = normalized.iter().flatten().copied().fold(f32::INFINITY, f32::min);
+    normalized.into_iter().map(|score| score.unwrap_or(lowest)).collect()
+}
+
+/// Row ids and one score column of a search, in result order
+pub async fn search_hits(mut scanner: Scanner, score_column: &str) -> lance::Result<Vec<(u64, f32)>> {
+    // Only the row ids and scores are needed; rows are read once fused
+    scanner.project::<&str>(&[])?;
+    scanner.with_row_id();
+
+    let batches: Vec<RecordBatch> = scanner.try_into_stream().await?.try_collect().await?;
+
+    let mut hits = Vec::new();
+    for batch in batches {
+        let row_ids = row_id_column(&batch)?;
+        let scores = batch.column_by_name(score_column)
+            .and_then(|column| column.as_any().downcast_ref::<Float32Array>())
+            .ok_or_else(|| ArrowError::SchemaError(format!("{} is not a Float32 column", score_column)))?;
+        hits.extend(row_ids.values().iter().copied().zip(scores.values().iter().copied()));
+    }
+
+    Ok(hits)
+}
+
+fn row_id_column(batch: &RecordBatch) -> lance::Result<&UInt64Array> {
+    batch.column_by_name("_rowid")
+        .and_then(|column| column.as_any().downcast_ref::<UInt64Array>())
+        .ok_or_else(|| ArrowError::SchemaError("_rowid is missing or not a UInt64 column".into()).into())
+}
+
+/// The `columns` of the rows with `row_ids`, in the order of `row_ids`.
+///
+/// The row ids are taken along with the rows, and the rows are put in order
+/// by matching them up rather than by relying on the order `take_rows` returns.
+pub async fn take_in_order(dataset: &Dataset, row_ids: &[u64], columns: &[String]) -> lance::Result<RecordBatch> {
+    let mut projected: Vec<&str> = columns.iter().map(String::as_str).collect();
+    projected.push("_rowid");
+    let batch = dataset.take_rows(row_ids, ProjectionRequest::from_columns(projected, dataset.schema())).await?;
+
+    let positions: HashMap<u64, u32> = row_id_column(&batch)?
+        .values()
+        .iter()
+        .enumerate()
+        .map(|(position, row_id)| (*row_id, position as u32))
+        .collect();
+    let indices = row_ids.iter()
+        .map(|row_id| positions.get(row_id).copied()
+            .ok_or_else(|| ArrowError::InvalidArgumentError(format!("Row id {} not found", row_id))))
+        .collect::<Result<Vec<u32>, _>>()?;
+
+    let mut ordered = take_record_batch(&batch, &UInt32Array::from(indices))?;
+    let row_id_index = ordered.schema().index_of("_rowid")?;
+    ordered.remove_column(row_id_index);
+
+    Ok(ordered)
+}
+
+/// Fill in the vector distance of candidates only the text search found
+pub async fn fill_distances(
+    dataset: &Dataset,
+    rows: &mut [FusedRow],
+    column: &str,
+    metric: MetricType,
+    query: &[f32],
+) -> lance::Result<()> {
+    let missing: Vec<u64> = rows.iter()
+        .filter(|row| row.distance.is_none())
+        .map(|row| row.row_id)
+        .collect();
+    if missing.is_empty() {
+        return Ok(());
+    }
+
+    let batch = take_in_order(dataset, &missing, &[column.to_string()]).await?;
+    let vectors = batch.column(0).as_any().downcast_ref::<FixedSizeListArray>()
+        .ok_or_else(|| ArrowError::SchemaError(format!("{} is not a vector column", column)))?;
+
+    let distances: HashMap<u64, f32> = missing.iter()
+        .enumerate()
+        .map(|(i, row_id)| (*row_id, distance(metric, query, &vector_values(&vectors.value(i)))))
+        .collect();
+    for row in rows.iter_mut().filter(|row| row.distance.is_none()) {
+        row.distance = distances.get(&row.row_id).copied();
+    }
+
+    Ok(())
+}
+
+fn vector_values(values: &ArrayRef) -> Vec<f32> {
+    if let Some(half_array) = values.as_any().downcast_ref::<Float16Array>() {
+        half_array.values().iter().map(|value| value.to_f32()).collect()
+    } else if let Some(byte_array) = values.as_any().downcast_ref::<UInt8Array>() {
+        byte_array.values().iter().map(|value| *value as f32).collect()
+    } else {
+        values.as_any().downcast_ref::<Float32Array>()
+            .map(|float_array| float_array.values().to_vec())
+            .unwrap_or_default()
+    }
+}
+
+/// Distance between two vectors, computed the way Lance does for the metric
+pub fn distance(metric: MetricType, a: &[f32], b: &[f32]) -> f32 {
+    let dot = || a.iter().zip(b).map(|(x, y)| x * y).sum::<f32>();
+
+    match metric {
+        MetricType::L2 => a.iter().zip(b).map(|(x, y)| (x - y).powi(2)).sum(),
+        MetricType::Cosine => {
+            let norm_a = a.iter().map(|x| x * x).sum::<f32>().sqrt();
+            let norm_b = b.iter().map(|x| x * x).sum::<f32>().sqrt();
+            if norm_a == 0.0 || norm_b == 0.0 {
+                1.0
+            } else {
+                1.0 - dot() / (norm_a * norm_b)
+            }
+        }
+        MetricType::Dot => 1.0 - dot(),
+        // Binary vectors hold one byte per element
+        MetricType::Hamming => a.iter()
+            .zip(b)
+            .map(|(x, y)| ((*x as u8) ^ (*y as u8)).count_ones() as f32)
+            .sum(),
+    }
+}
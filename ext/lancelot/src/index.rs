@@ -0,0 +1,223 @@
+use magnus::{Error, RHash};
+use arrow_schema::{DataType, Schema as ArrowSchema};
+use lance::index::vector::VectorIndexParams;
+use lance_index::IndexType;
+use lance_index::scalar::{BuiltinIndexType, InvertedIndexParams, ScalarIndexParams};
+use lance_index::vector::hnsw::builder::HnswBuildParams;
+use lance_index::vector::ivf::IvfBuildParams;
+use lance_index::vector::pq::PQBuildParams;
+use lance_index::vector::sq::builder::SQBuildParams;
+use lance_linalg::distance::MetricType;
+
+use crate::options::{get_opt, get_opt_string};
+use crate::schema::METRIC_META_KEY;
+
+pub fn parse_metric(name: &str) -> Result<MetricType, Error> {
+    match name.to_lowercase().as_str() {
+        "l2" | "euclidean" => Ok(MetricType::L2),
+        "cosine" => Ok(MetricType::Cosine),
+        "dot" => Ok(MetricType::Dot),
+        "hamming" => Ok(MetricType::Hamming),
+        other => Err(Error::new(
+            magnus::exception::arg_error(),
+            format!("Unknown distance metric: {}", other)
+        ))
+    }
+}
+
+/// The metric given in the options, else the column's default from the schema, if any
+pub fn resolve_metric(options: Option<&RHash>, schema: &ArrowSchema, column: &str) -> Result<Option<MetricType>, Error> {
+    if let Some(metric) = options.map(|o| get_opt_string(o, "metric")).transpose()?.flatten() {
+        return parse_metric(&metric).map(Some);
+    }
+
+    schema.field_with_name(column)
+        .ok()
+        .and_then(|field| field.metadata().get(METRIC_META_KEY))
+        .map(|metric| parse_metric(metric))
+        .transpose()
+}
+
+/// The `name` and `replace` options shared by the create_*_index methods.
+///
+/// Lance names unnamed indexes `{column}_idx`; replacing is the default.
+pub fn index_name_and_replace(options: &RHash) -> Result<(Option<String>, bool), Error> {
+    Ok((get_opt_string(options, "name")?, get_opt(options, "replace")?.unwrap_or(true)))
+}
+
+/// Default number of IVF partitions for a table of the given size
+fn default_num_partitions(num_rows: usize) -> usize {
+    // Use fewer partitions for small datasets, and about 4096 rows per partition for large ones
+    if num_rows < 256 {
+        std::cmp::max(1, num_rows / 4)
+    } else {
+        (num_rows / 4096).clamp(256, 4096)
+    }
+}
+
+/// Default number of PQ sub-vectors, which must divide the dimension evenly
+fn default_num_sub_vectors(dimension: usize) -> usize {
+    if dimension % 16 == 0 {
+        dimension / 16
+    } else if dimension % 8 == 0 {
+        dimension / 8
+    } else {
+        1
+    }
+}
+
+/// Build vector index parameters from the options given to create_vector_index.
+///
+/// Anything not given is derived from the row count and vector dimension.
+pub fn build_vector_index_params(
+    options: &RHash,
+    metric: MetricType,
+    num_rows: usize,
+    dimension: usize,
+) -> Result<VectorIndexParams, Error> {
+    let index_type = get_opt_string(options, "type")?.unwrap_or_else(|| "ivf_flat".to_string());
+
+    let mut ivf = IvfBuildParams::new(
+        get_opt(options, "num_partitions")?.unwrap_or_else(|| default_num_partitions(num_rows))
+    );
+    if let Some(max_iterations) = get_opt::<usize>(options, "max_iterations")? {
+        ivf.max_iters = max_iterations;
+    }
+    if let Some(sample_rate) = get_opt::<usize>(options, "sample_rate")? {
+        ivf.sample_rate = sample_rate;
+    }
+
+    let pq = || -> Result<PQBuildParams, Error> {
+        let mut pq = PQBuildParams::new(
+            get_opt(options, "num_sub_vectors")?.unwrap_or_else(|| default_num_sub_vectors(dimension)),
+            get_opt(options, "num_bits")?.unwrap_or(8),
+        );
+        if let Some(max_iterations) = get_opt::<usize>(options, "max_iterations")? {
+            pq.max_iters = max_iterations;
+        }
+        if let Some(sample_rate) = get_opt::<usize>(options, "sample_rate")? {
+            pq.sample_rate = sample_rate;
+        }
+        Ok(pq)
+    };
+
+    let sq = || -> Result<SQBuildParams, Error> {
+        let mut sq = SQBuildParams::default();
+        if let Some(num_bits) = get_opt::<u16>(options, "num_bits")? {
+            sq.num_bits = num_bits;
+        }
+        if let Some(sample_rate) = get_opt::<usize>(options, "sample_rate")? {
+            sq.sample_rate = sample_rate;
+        }
+        Ok(sq)
+    };
+
+    let hnsw = || -> Result<HnswBuildParams, Error> {
+        let mut hnsw = HnswBuildParams::default();
+        if let Some(m) = get_opt::<usize>(options, "m")? {
+            hnsw = hnsw.num_edges(m);
+        }
+        if let Some(ef_construction) = get_opt::<usize>(options, "ef_construction")? {
+            hnsw = hnsw.ef_construction(ef_construction);
+        }
+        Ok(hnsw)
+    };
+
+    let params = match index_type.as_str() {
+        "ivf_flat" => VectorIndexParams::with_ivf_flat_params(metric, ivf),
+        "ivf_pq" => VectorIndexParams::with_ivf_pq_params(metric, ivf, pq()?),
+        "ivf_sq" => VectorIndexParams::with_ivf_sq_params(metric, ivf, sq()?),
+        "ivf_hnsw_sq" => VectorIndexParams::with_ivf_hnsw_sq_params(metric, ivf, hnsw()?, sq()?),
+        "ivf_hnsw_pq" => VectorIndexParams::with_ivf_hnsw_pq_params(metric, ivf, hnsw()?, pq()?),
+        other => return Err(Error::new(
+            magnus::exception::arg_error(),
+            format!("Unknown vector index type: {}", other)
+        ))
+    };
+
+    Ok(params)
+}
+
+/// Index type and parameters for create_scalar_index, checked against the column's type.
+///
+/// BTree suits high-cardinality columns and range filters, Bitmap suits
+/// low-cardinality columns, and LabelList serves `array_has_any`/`array_has_all`
+/// filters on list columns.
+pub fn build_scalar_index_params(
+    options: &RHash,
+    column: &str,
+    data_type: &DataType,
+) -> Result<(IndexType, ScalarIndexParams), Error> {
+    let name = get_opt_string(options, "type")?.unwrap_or_else(|| "btree".to_string()).to_lowercase();
+    let is_list = matches!(data_type, DataType::List(_));
+    let is_scalar = !is_list && !matches!(data_type, DataType::FixedSizeList(_, _));
+
+    let (index_type, builtin, supported) = match name.as_str() {
+        "btree" => (IndexType::BTree, BuiltinIndexType::BTree, is_scalar),
+        "bitmap" => (IndexType::Bitmap, BuiltinIndexType::Bitmap, is_scalar),
+        "label_list" => (IndexType::LabelList, BuiltinIndexType::LabelList, is_list),
+        other => return Err(Error::new(
+            magnus::exception::arg_error(),
+            format!("Unknown scalar index type: {}", other)
+        ))
+    };
+
+    if !supported {
+        return Err(Error::new(
+            magnus::exception::arg_error(),
+            format!("Cannot create a {} index on column {} of type {}", name, column, data_type)
+        ));
+    }
+
+    Ok((index_type, ScalarIndexParams::for_builtin(builtin)))
+}
+
+/// Build inverted index parameters from the options given to create_text_index
+pub fn build_inverted_index_params(options: &RHash) -> Result<InvertedIndexParams, Error> {
+    let mut params = InvertedIndexParams::default();
+
+    if let Some(tokenizer) = get_opt_string(options, "base_tokenizer")? {
+        // Jieba and Lindera need a model; bare names pick the default one
+        let tokenizer = match tokenizer.to_lowercase().as_str() {
+            "jieba" => "jieba/default".to_string(),
+            "lindera" => "lindera/ipadic".to_string(),
+            "simple" | "whitespace" | "raw" | "ngram" => tokenizer.to_lowercase(),
+            other if other.starts_with("jieba/") || other.starts_with("lindera/") => tokenizer,
+            other => return Err(Error::new(
+                magnus::exception::arg_error(),
+                format!("Unknown tokenizer: {}", other)
+            ))
+        };
+        params = params.base_tokenizer(tokenizer);
+    }
+    if let Some(language) = get_opt_string(options, "language")? {
+        // Lance expects Tantivy's language names, e.g. "English" or "German"
+        let mut chars = language.chars();
+        let language = match chars.next() {
+            Some(first) => first.to_uppercase().chain(chars.flat_map(char::to_lowercase)).collect::<String>(),
+            None => language,
+        };
+        params = params.language(&language)
+            .map_err(|_| Error::new(magnus::exception::arg_error(), format!("Unsupported language: {}", language)))?;
+    }
+    if let Some(stem) = get_opt::<bool>(options, "stem")? {
+        params = params.stem(stem);
+    }
+    if let Some(remove_stop_words) = get_opt::<bool>(options, "remove_stop_words")? {
+        params = params.remove_stop_words(remove_stop_words);
+    }
+    if let Some(ascii_folding) = get_opt::<bool>(options, "ascii_folding")? {
+        params = params.ascii_folding(ascii_folding);
+    }
+    if let Some(lower_case) = get_opt::<bool>(options, "lower_case")? {
+        params = params.lower_case(lower_case);
+    }
+    if let Some(max_token_length) = get_opt::<usize>(options, "max_token_length")? {
+        params = params.max_token_length(Some(max_token_length));
+    }
+    if let Some(with_position) = get_opt::<bool>(options, "with_position")? {
+        params = params.with_position(with_position);
+    }
+
+    Ok(params)
+}
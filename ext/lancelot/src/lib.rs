@@ -1,8 +1,17 @@
 use magnus::{define_module, Error, Ruby, Module};
 
+mod checkpoint;
+mod cleanup;
 mod dataset;
+mod diff;
+mod hybrid;
+mod index;
 mod schema;
 mod conversion;
+mod options;
+mod query;
+mod scanner;
+mod sql;
 
 use dataset::LancelotDataset;
 
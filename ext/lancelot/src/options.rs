@@ -0,0 +1,112 @@
+use magnus::{Error, RHash, Symbol, TryConvert, Value, value::ReprValue};
+use lance::dataset::WriteParams;
+use lance::dataset::optimize::CompactionOptions;
+use lance_index::optimize::OptimizeOptions;
+use lance_file::version::LanceFileVersion;
+
+/// Fetch an optional value from a Ruby options hash, treating nil as absent
+pub fn get_opt<T: TryConvert>(options: &RHash, key: &str) -> Result<Option<T>, Error> {
+    match options.get(Symbol::new(key)) {
+        Some(value) if !value.is_nil() => T::try_convert(value).map(Some),
+        _ => Ok(None),
+    }
+}
+
+/// Fetch an optional string option, accepting either a String or a Symbol
+pub fn get_opt_string(options: &RHash, key: &str) -> Result<Option<String>, Error> {
+    match options.get(Symbol::new(key)) {
+        Some(value) if !value.is_nil() => value_to_string(value).map(Some),
+        _ => Ok(None),
+    }
+}
+
+/// Fetch an optional Ruby Time as microseconds since the epoch
+pub fn get_opt_time_micros(options: &RHash, key: &str) -> Result<Option<i64>, Error> {
+    match options.get(Symbol::new(key)) {
+        Some(value) if !value.is_nil() => {
+            let seconds: i64 = value.funcall("to_i", ())?;
+            let micros: i64 = value.funcall("usec", ())?;
+            Ok(Some(seconds * 1_000_000 + micros))
+        }
+        _ => Ok(None),
+    }
+}
+
+pub fn value_to_string(value: Value) -> Result<String, Error> {
+    if let Some(symbol) = Symbol::from_value(value) {
+        Ok(symbol.name()?.to_string())
+    } else {
+        String::try_convert(value)
+    }
+}
+
+/// Build Lance write parameters from the options given to create/add_data
+pub fn build_write_params(options: Option<RHash>) -> Result<WriteParams, Error> {
+    let mut params = WriteParams::default();
+
+    let options = match options {
+        Some(options) => options,
+        None => return Ok(params),
+    };
+
+    if let Some(max_rows_per_file) = get_opt::<usize>(&options, "max_rows_per_file")? {
+        params.max_rows_per_file = max_rows_per_file;
+    }
+    if let Some(max_rows_per_group) = get_opt::<usize>(&options, "max_rows_per_group")? {
+        params.max_rows_per_group = max_rows_per_group;
+    }
+    if let Some(max_bytes_per_file) = get_opt::<usize>(&options, "max_bytes_per_file")? {
+        params.max_bytes_per_file = max_bytes_per_file;
+    }
+    if let Some(version) = get_opt_string(&options, "data_storage_version")? {
+        let version = version.parse::<LanceFileVersion>()
+            .map_err(|e| Error::new(magnus::exception::arg_error(), e.to_string()))?;
+        params.data_storage_version = Some(version);
+    }
+    if let Some(stable_row_ids) = get_opt::<bool>(&options, "enable_move_stable_row_ids")? {
+        params.enable_move_stable_row_ids = stable_row_ids;
+    }
+
+    Ok(params)
+}
+
+/// Build compaction options, falling back to Lance's defaults for anything not given
+pub fn build_compaction_options(options: &RHash) -> Result<CompactionOptions, Error> {
+    let mut compaction = CompactionOptions::default();
+
+    if let Some(target_rows) = get_opt::<usize>(options, "target_rows_per_fragment")? {
+        compaction.target_rows_per_fragment = target_rows;
+    }
+    if let Some(max_rows_per_group) = get_opt::<usize>(options, "max_rows_per_group")? {
+        compaction.max_rows_per_group = max_rows_per_group;
+    }
+    if let Some(materialize_deletions) = get_opt::<bool>(options, "materialize_deletions")? {
+        compaction.materialize_deletions = materialize_deletions;
+    }
+    if let Some(threshold) = get_opt::<f32>(options, "materialize_deletions_threshold")? {
+        compaction.materialize_deletions_threshold = threshold;
+    }
+    if let Some(num_threads) = get_opt::<usize>(options, "num_threads")? {
+        compaction.num_threads = Some(num_threads);
+    }
+
+    Ok(compaction)
+}
+
+/// Build index optimisation options. By default, rows added since an index
+/// was built go into a new delta merged with the latest one.
+pub fn build_optimize_options(options: &RHash) -> Result<OptimizeOptions, Error> {
+    let mut optimize = OptimizeOptions::default();
+
+    if let Some(num_indices_to_merge) = get_opt::<usize>(options, "num_indices_to_merge")? {
+        optimize.num_indices_to_merge = num_indices_to_merge;
+    }
+    if let Some(index_names) = get_opt::<Vec<String>>(options, "index_names")? {
+        optimize.index_names = Some(index_names);
+    }
+    if let Some(retrain) = get_opt::<bool>(options, "retrain")? {
+        optimize.retrain = retrain;
+    }
+
+    Ok(optimize)
+}
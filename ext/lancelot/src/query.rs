@@ -0,0 +1,185 @@
+use magnus::{Error, RArray, RHash, Symbol, TryConvert, Value, value::ReprValue};
+use lance_index::scalar::inverted::query::{
+    BooleanQuery, BoostQuery, FtsQuery, MatchQuery, MultiMatchQuery, Occur, Operator, PhraseQuery,
+};
+
+use crate::options::{get_opt, get_opt_string, value_to_string};
+
+/// Largest number of clause combinations a `minimum_should_match` may expand into
+const MAX_SHOULD_COMBINATIONS: usize = 64;
+
+fn arg_error(message: impl Into<String>) -> Error {
+    Error::new(magnus::exception::arg_error(), message.into())
+}
+
+fn to_arg_error(e: lance::Error) -> Error {
+    arg_error(e.to_string())
+}
+
+fn parse_operator(options: &RHash) -> Result<Operator, Error> {
+    match get_opt_string(options, "operator")?.as_deref().map(str::to_lowercase).as_deref() {
+        None | Some("or") => Ok(Operator::Or),
+        Some("and") => Ok(Operator::And),
+        Some(other) => Err(arg_error(format!("Unknown operator: {}", other))),
+    }
+}
+
+/// Convert a query built with `Lancelot::Query` into a Lance full-text query
+pub fn parse_fts_query(query: RHash) -> Result<FtsQuery, Error> {
+    let query_type = get_opt_string(&query, "type")?
+        .ok_or_else(|| arg_error("Query must include a :type"))?;
+
+    match query_type.as_str() {
+        "match" => {
+            let terms: String = query.fetch(Symbol::new("terms"))?;
+            let mut match_query = MatchQuery::new(terms)
+                .with_column(get_opt_string(&query, "column")?)
+                .with_operator(parse_operator(&query)?);
+            if let Some(boost) = get_opt::<f32>(&query, "boost")? {
+                match_query = match_query.with_boost(boost);
+            }
+            // :auto leaves the edit distance to Lance, based on each term's length
+            match query.get(Symbol::new("fuzziness")) {
+                Some(fuzziness) if value_to_string(fuzziness).is_ok_and(|f| f == "auto") => {
+                    match_query = match_query.with_fuzziness(None);
+                }
+                Some(fuzziness) if !fuzziness.is_nil() => {
+                    match_query = match_query.with_fuzziness(Some(u32::try_convert(fuzziness)?));
+                }
+                _ => {}
+            }
+            if let Some(max_expansions) = get_opt::<usize>(&query, "max_expansions")? {
+                match_query = match_query.with_max_expansions(max_expansions);
+            }
+            if let Some(prefix_length) = get_opt::<u32>(&query, "prefix_length")? {
+                match_query = match_query.with_prefix_length(prefix_length);
+            }
+            Ok(FtsQuery::Match(match_query))
+        }
+        "phrase" => {
+            let terms: String = query.fetch(Symbol::new("terms"))?;
+            let mut phrase_query = PhraseQuery::new(terms)
+                .with_column(get_opt_string(&query, "column")?);
+            if let Some(slop) = get_opt::<u32>(&query, "slop")? {
+                phrase_query = phrase_query.with_slop(slop);
+            }
+            Ok(FtsQuery::Phrase(phrase_query))
+        }
+        "multi_match" => {
+            let terms: String = query.fetch(Symbol::new("terms"))?;
+            let columns: Vec<String> = query.fetch(Symbol::new("columns"))?;
+            let mut multi_match = MultiMatchQuery::try_new(terms, columns)
+                .map_err(to_arg_error)?
+                .with_operator(parse_operator(&query)?);
+            if let Some(boosts) = get_opt::<Vec<f32>>(&query, "boosts")? {
+                multi_match = multi_match.try_with_boosts(boosts).map_err(to_arg_error)?;
+            }
+            Ok(FtsQuery::MultiMatch(multi_match))
+        }
+        "boolean" => parse_boolean_query(&query),
+        "boost" => {
+            let positive = parse_fts_query(query.fetch(Symbol::new("positive"))?)?;
+            let negative = parse_fts_query(query.fetch(Symbol::new("negative"))?)?;
+            let negative_boost = get_opt::<f32>(&query, "negative_boost")?;
+            Ok(FtsQuery::Boost(BoostQuery::new(positive, negative, negative_boost)))
+        }
+        other => Err(arg_error(format!("Unknown query type: {}", other))),
+    }
+}
+
+fn parse_clauses(query: &RHash, key: &str) -> Result<Vec<FtsQuery>, Error> {
+    match get_opt::<RArray>(query, key)? {
+        Some(clauses) => clauses
+            .into_iter()
+            .map(|clause: Value| parse_fts_query(RHash::try_convert(clause)?))
+            .collect(),
+        None => Ok(Vec::new()),
+    }
+}
+
+fn parse_boolean_query(query: &RHash) -> Result<FtsQuery, Error> {
+    let must = parse_clauses(query, "must")?;
+    let should = parse_clauses(query, "should")?;
+    let must_not = parse_clauses(query, "must_not")?;
+    let minimum_should_match = get_opt::<usize>(query, "minimum_should_match")?.unwrap_or(0);
+
+    if minimum_should_match > should.len() {
+        return Err(arg_error(format!(
+            "minimum_should_match is {} but there are only {} should clauses",
+            minimum_should_match,
+            should.len()
+        )));
+    }
+
+    // With a must clause, Lance treats should clauses as optional, otherwise
+    // at least one of them has to match
+    let has_must = !must.is_empty();
+
+    let mut clauses: Vec<(Occur, FtsQuery)> = Vec::new();
+    clauses.extend(must.into_iter().map(|q| (Occur::Must, q)));
+    clauses.extend(must_not.into_iter().map(|q| (Occur::MustNot, q)));
+
+    match minimum_should_match {
+        0 => clauses.extend(should.into_iter().map(|q| (Occur::Should, q))),
+        1 if !has_must => clauses.extend(should.into_iter().map(|q| (Occur::Should, q))),
+        1 => clauses.push((Occur::Must, FtsQuery::Boolean(BooleanQuery::new(
+            should.into_iter().map(|q| (Occur::Should, q))
+        )))),
+        k if k == should.len() => clauses.extend(should.into_iter().map(|q| (Occur::Must, q))),
+        k => {
+            // Lance has no minimum_should_match, so require any one of the
+            // combinations of k should clauses
+            if combination_count(should.len(), k, MAX_SHOULD_COMBINATIONS).is_none() {
+                return Err(arg_error(format!(
+                    "minimum_should_match {} of {} should clauses is too many combinations",
+                    k,
+                    should.len()
+                )));
+            }
+            let alternatives = combinations(should.len(), k).into_iter().map(|combination| {
+                let required = combination.into_iter().map(|i| (Occur::Must, should[i].clone()));
+                (Occur::Should, FtsQuery::Boolean(BooleanQuery::new(required)))
+            });
+            clauses.push((Occur::Must, FtsQuery::Boolean(BooleanQuery::new(alternatives))));
+        }
+    }
+
+    Ok(FtsQuery::Boolean(BooleanQuery::new(clauses)))
+}
+
+/// The number of ways of picking `k` of `n` items, or None if it is over
+/// `max`. Counts without building the combinations, and stops as soon as the
+/// count passes `max`.
+fn combination_count(n: usize, k: usize, max: usize) -> Option<usize> {
+    let k = k.min(n - k);
+    let mut count: usize = 1;
+    // C(n, i) only grows for i up to n / 2, so every step stays below max * n
+    for i in 0..k {
+        count = count.checked_mul(n - i)? / (i + 1);
+        if count > max {
+            return None;
+        }
+    }
+    Some(count)
+}
+
+/// Every way of picking `k` of the indexes `0..n`, in order
+fn combinations(n: usize, k: usize) -> Vec<Vec<usize>> {
+    let mut result = Vec::new();
+    let mut current = Vec::with_capacity(k);
+
+    fn pick(start: usize, n: usize, k: usize, current: &mut Vec<usize>, result: &mut Vec<Vec<usize>>) {
+        if current.len() == k {
+            result.push(current.clone());
+            return;
+        }
+        for i in start..n {
+            current.push(i);
+            pick(i + 1, n, k, current, result);
+            current.pop();
+        }
+    }
+
+    pick(0, n, k, &mut current, &mut result);
+    result
+}
@@ -0,0 +1,200 @@
+use magnus::{Error, RHash, Symbol};
+use arrow_array::RecordBatch;
+use arrow_schema::Schema as ArrowSchema;
+use datafusion::config::ConfigOptions;
+use datafusion::physical_optimizer::limit_pushdown::LimitPushdown;
+use datafusion::physical_optimizer::PhysicalOptimizerRule;
+use datafusion::physical_plan::displayable;
+use futures::stream::TryStreamExt;
+use lance::dataset::scanner::{ColumnOrdering, Scanner};
+use lance_datafusion::exec::{execute_plan, LanceExecutionOptions};
+
+use crate::options::{get_opt, get_opt_string};
+use crate::sql::{bind_filter, parse_params, Params};
+
+fn to_runtime_error(e: lance::Error) -> Error {
+    Error::new(magnus::exception::runtime_error(), e.to_string())
+}
+
+/// What a read returns: its rows, or with `explain: true` the physical plan
+pub enum ScanOutput {
+    Batches(Vec<RecordBatch>),
+    Plan(String),
+}
+
+/// Run the scanner, or describe its physical plan if the options ask to `explain`.
+///
+/// Lance puts the limit in a separate node above any sort, so DataFusion's
+/// limit pushdown is applied first to turn a sorted, limited scan into a
+/// top-k sort. Explained plans are the plans that run.
+pub async fn execute(scanner: &Scanner, options: Option<&RHash>) -> Result<ScanOutput, Error> {
+    let explain = match options {
+        Some(options) => get_opt::<bool>(options, "explain")?.unwrap_or(false),
+        None => false,
+    };
+
+    let plan = scanner.create_plan().await.map_err(to_runtime_error)?;
+    let plan = LimitPushdown::new()
+        .optimize(plan, &ConfigOptions::default())
+        .map_err(|e| Error::new(magnus::exception::runtime_error(), e.to_string()))?;
+
+    if explain {
+        return Ok(ScanOutput::Plan(displayable(plan.as_ref()).indent(true).to_string()));
+    }
+
+    let batches = execute_plan(plan, LanceExecutionOptions::default())
+        .map_err(|e| Error::new(magnus::exception::runtime_error(), e.to_string()))?
+        .try_collect::<Vec<_>>()
+        .await
+        .map_err(|e| Error::new(magnus::exception::runtime_error(), e.to_string()))?;
+
+    Ok(ScanOutput::Batches(batches))
+}
+
+/// Filter the scanner, binding `params` to the filter's placeholders
+pub fn apply_filter(scanner: &mut Scanner, schema: &ArrowSchema, filter: &str, params: Option<Params>) -> Result<(), Error> {
+    match params {
+        Some(params) => {
+            scanner.filter_expr(bind_filter(filter, params, schema)?);
+        }
+        None => {
+            scanner.filter(filter).map_err(to_runtime_error)?;
+        }
+    }
+
+    Ok(())
+}
+
+/// The `filter_params` option: an Array for `?` placeholders, a Hash for `:name` ones
+pub fn filter_params(options: &RHash) -> Result<Option<Params>, Error> {
+    match options.get(Symbol::new("filter_params")) {
+        Some(params) => parse_params(params),
+        None => Ok(None),
+    }
+}
+
+/// Apply the `filter` and `filter_params` options, along with `prefilter` for searches.
+///
+/// Searches prefilter by default so a restrictive filter still returns up to
+/// `limit` rows; `prefilter: false` filters the top results instead.
+pub fn apply_filter_options(scanner: &mut Scanner, schema: &ArrowSchema, options: &RHash) -> Result<(), Error> {
+    if let Some(filter) = get_opt_string(options, "filter")? {
+        apply_filter(scanner, schema, &filter, filter_params(options)?)?;
+        scanner.prefilter(get_opt::<bool>(options, "prefilter")?.unwrap_or(true));
+    }
+
+    Ok(())
+}
+
+/// The `offset` option: how many rows to skip before the first one returned
+pub fn offset_option(options: Option<&RHash>) -> Result<Option<i64>, Error> {
+    match options {
+        Some(options) => get_opt::<i64>(options, "offset"),
+        None => Ok(None),
+    }
+}
+
+/// Apply query-time ANN tuning options to a scanner that already has a nearest() query
+pub fn apply_ann_options(scanner: &mut Scanner, options: &RHash) -> Result<(), Error> {
+    // A fixed number of partitions to probe, or a range Lance probes adaptively
+    if let Some(nprobes) = get_opt::<usize>(options, "nprobes")? {
+        scanner.minimum_nprobes(nprobes);
+        scanner.maximum_nprobes(nprobes);
+    }
+    if let Some(minimum_nprobes) = get_opt::<usize>(options, "minimum_nprobes")? {
+        scanner.minimum_nprobes(minimum_nprobes);
+    }
+    if let Some(maximum_nprobes) = get_opt::<usize>(options, "maximum_nprobes")? {
+        scanner.maximum_nprobes(maximum_nprobes);
+    }
+    if let Some(refine_factor) = get_opt::<u32>(options, "refine_factor")? {
+        scanner.refine(refine_factor);
+    }
+    if let Some(ef) = get_opt::<usize>(options, "ef")? {
+        scanner.ef(ef);
+    }
+    if let Some(use_index) = get_opt::<bool>(options, "use_index")? {
+        scanner.use_index(use_index);
+    }
+    // Fast search only looks at indexed data, skipping rows appended since the index was built
+    if get_opt::<bool>(options, "fast_search")?.unwrap_or(false) {
+        scanner.fast_search();
+    }
+
+    Ok(())
+}
+
+/// The `select` columns, or every column except the `exclude` ones, if either is given
+pub fn projected_columns(schema: &ArrowSchema, options: &RHash) -> Result<Option<Vec<String>>, Error> {
+    let select: Option<Vec<String>> = get_opt(options, "select")?;
+    let exclude: Option<Vec<String>> = get_opt(options, "exclude")?;
+
+    match (select, exclude) {
+        (None, None) => Ok(None),
+        (Some(_), Some(_)) => Err(Error::new(
+            magnus::exception::arg_error(),
+            "Cannot specify both select and exclude"
+        )),
+        (Some(select), None) => Ok(Some(select)),
+        (None, Some(exclude)) => {
+            if let Some(unknown) = exclude.iter().find(|column| schema.field_with_name(column).is_err()) {
+                return Err(Error::new(
+                    magnus::exception::arg_error(),
+                    format!("Column {} not found", unknown)
+                ));
+            }
+            Ok(Some(schema.fields()
+                .iter()
+                .map(|field| field.name().clone())
+                .filter(|name| !exclude.contains(name))
+                .collect()))
+        }
+    }
+}
+
+/// Project the scanner onto the columns chosen with `select` or `exclude`,
+/// so the others are never read from disk, adding `_rowid` if `with_row_id`
+pub fn apply_projection(scanner: &mut Scanner, schema: &ArrowSchema, options: &RHash) -> Result<(), Error> {
+    if let Some(columns) = projected_columns(schema, options)? {
+        scanner.project(&columns).map_err(to_runtime_error)?;
+    }
+    if get_opt::<bool>(options, "with_row_id")?.unwrap_or(false) {
+        scanner.with_row_id();
+    }
+
+    Ok(())
+}
+
+/// Sort by the `order_by` columns, each a `[column, "asc" | "desc"]` pair.
+///
+/// Ascending sorts put nulls last and descending sorts put them first. Lance
+/// sorts inside the scan plan, and `execute` pushes any limit into the sort
+/// so only the top rows are kept.
+pub fn apply_order_by(scanner: &mut Scanner, schema: &ArrowSchema, options: &RHash) -> Result<(), Error> {
+    let Some(order_by) = get_opt::<Vec<(String, String)>>(options, "order_by")? else {
+        return Ok(());
+    };
+
+    let orderings = order_by.into_iter()
+        .map(|(column, direction)| {
+            if schema.field_with_name(&column).is_err() {
+                return Err(Error::new(
+                    magnus::exception::arg_error(),
+                    format!("Column {} not found", column)
+                ));
+            }
+            match direction.as_str() {
+                "asc" => Ok(ColumnOrdering::asc_nulls_last(column)),
+                "desc" => Ok(ColumnOrdering::desc_nulls_first(column)),
+                other => Err(Error::new(
+                    magnus::exception::arg_error(),
+                    format!("Unknown sort direction: {}", other)
+                )),
+            }
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+
+    scanner.order_by(Some(orderings)).map_err(to_runtime_error)?;
+
+    Ok(())
+}
@@ -1,50 +1,110 @@
 use magnus::{Error, RHash, Symbol, Value, TryConvert, r_hash::ForEach, value::ReprValue};
 use arrow_schema::{DataType, Field, Schema as ArrowSchema};
+use std::collections::HashMap;
 use std::sync::Arc;
 
-pub fn build_arrow_schema(schema_hash: RHash) -> Result<ArrowSchema, Error> {
-    let mut fields = Vec::new();
+use crate::options::{get_opt, get_opt_string};
 
-    schema_hash.foreach(|key: Symbol, value: Value| {
-        let field_name = key.name()?.to_string();
-        
-        let data_type = if value.is_kind_of(magnus::class::hash()) {
-            let hash = RHash::from_value(value)
-                .ok_or_else(|| Error::new(magnus::exception::arg_error(), "Invalid hash value"))?;
-            let type_str: String = hash.fetch(Symbol::new("type"))?;
-            
-            match type_str.as_str() {
-                "vector" => {
-                    let dimension: i32 = hash.fetch(Symbol::new("dimension"))?;
-                    DataType::FixedSizeList(
-                        Arc::new(Field::new("item", DataType::Float32, true)),
-                        dimension,
-                    )
+/// Field metadata key holding the default distance metric of a vector column
+pub const METRIC_META_KEY: &str = "lancelot:metric";
+
+/// Map a Ruby type name to its Arrow DataType (vectors are handled separately)
+fn scalar_data_type(type_str: &str) -> Result<DataType, Error> {
+    match type_str {
+        "string" => Ok(DataType::Utf8),
+        "float32" => Ok(DataType::Float32),
+        "float64" => Ok(DataType::Float64),
+        "int32" => Ok(DataType::Int32),
+        "int64" => Ok(DataType::Int64),
+        "boolean" => Ok(DataType::Boolean),
+        "date" => Ok(DataType::Date32),
+        _ => Err(Error::new(
+            magnus::exception::arg_error(),
+            format!("Unknown field type: {}", type_str)
+        ))
+    }
+}
+
+/// Collect per-column encoding hints into Lance field metadata
+fn encoding_metadata(hash: &RHash) -> Result<HashMap<String, String>, Error> {
+    let mut metadata = HashMap::new();
+
+    if let Some(compression) = get_opt_string(hash, "compression")? {
+        metadata.insert("lance-encoding:compression".to_string(), compression);
+    }
+    if let Some(level) = get_opt::<i64>(hash, "compression_level")? {
+        metadata.insert("lance-encoding:compression-level".to_string(), level.to_string());
+    }
+    if let Some(encoding) = get_opt_string(hash, "structural_encoding")? {
+        metadata.insert("lance-encoding:structural-encoding".to_string(), encoding);
+    }
+    if let Some(divisor) = get_opt::<i64>(hash, "dict_divisor")? {
+        metadata.insert("lance-encoding:dict-divisor".to_string(), divisor.to_string());
+    }
+
+    Ok(metadata)
+}
+
+/// Parse a Ruby field definition (type name or options hash) into an Arrow type and field metadata
+pub fn parse_field_type(value: Value) -> Result<(DataType, HashMap<String, String>), Error> {
+    if value.is_kind_of(magnus::class::hash()) {
+        let hash = RHash::from_value(value)
+            .ok_or_else(|| Error::new(magnus::exception::arg_error(), "Invalid hash value"))?;
+        let type_str: String = hash.fetch(Symbol::new("type"))?;
+        let mut metadata = encoding_metadata(&hash)?;
+
+        let data_type = match type_str.as_str() {
+            "vector" => {
+                let dimension: i32 = hash.fetch(Symbol::new("dimension"))?;
+                let element_type = match get_opt_string(&hash, "element_type")?.as_deref() {
+                    None | Some("float32") => DataType::Float32,
+                    Some("float16") => DataType::Float16,
+                    Some("uint8") => DataType::UInt8,
+                    Some(other) => return Err(Error::new(
+                        magnus::exception::arg_error(),
+                        format!("Unsupported vector element type: {}", other)
+                    ))
+                };
+                if let Some(metric) = get_opt_string(&hash, "metric")? {
+                    crate::index::parse_metric(&metric)?;
+                    metadata.insert(METRIC_META_KEY.to_string(), metric.to_lowercase());
                 }
-                _ => return Err(Error::new(
-                    magnus::exception::arg_error(),
-                    format!("Unknown field type: {}", type_str)
-                ))
+                DataType::FixedSizeList(
+                    Arc::new(Field::new("item", element_type, true)),
+                    dimension,
+                )
             }
-        } else {
-            let type_str = String::try_convert(value)?;
-            match type_str.as_str() {
-                "string" => DataType::Utf8,
-                "float32" => DataType::Float32,
-                "float64" => DataType::Float64,
-                "int32" => DataType::Int32,
-                "int64" => DataType::Int64,
-                "boolean" => DataType::Boolean,
-                _ => return Err(Error::new(
-                    magnus::exception::arg_error(),
-                    format!("Unknown field type: {}", type_str)
-                ))
+            "list" => {
+                let element_type = get_opt_string(&hash, "element_type")?.unwrap_or_else(|| "string".to_string());
+                let element_type = match scalar_data_type(&element_type)? {
+                    data_type @ (DataType::Utf8 | DataType::Int32 | DataType::Int64) => data_type,
+                    _ => return Err(Error::new(
+                        magnus::exception::arg_error(),
+                        format!("Unsupported list element type: {}", element_type)
+                    ))
+                };
+                DataType::List(Arc::new(Field::new("item", element_type, true)))
             }
+            other => scalar_data_type(other)?
         };
 
-        fields.push(Field::new(field_name, data_type, true));
+        Ok((data_type, metadata))
+    } else {
+        let type_str = String::try_convert(value)?;
+        Ok((scalar_data_type(&type_str)?, HashMap::new()))
+    }
+}
+
+pub fn build_arrow_schema(schema_hash: RHash) -> Result<ArrowSchema, Error> {
+    let mut fields = Vec::new();
+
+    schema_hash.foreach(|key: Symbol, value: Value| {
+        let field_name = key.name()?.to_string();
+        let (data_type, metadata) = parse_field_type(value)?;
+
+        fields.push(Field::new(field_name, data_type, true).with_metadata(metadata));
         Ok(ForEach::Continue)
     })?;
 
     Ok(ArrowSchema::new(fields))
-}
\ No newline at end of file
+}
@@ -0,0 +1,235 @@
+use magnus::{Error, Float, Integer, RArray, RHash, RString, Ruby, Symbol, TryConvert, Value, r_hash::ForEach, value::ReprValue};
+use arrow_schema::{Field as ArrowField, Schema as ArrowSchema};
+use datafusion::common::tree_node::{Transformed, TreeNode};
+use datafusion::common::{DataFusionError, ScalarValue};
+use datafusion::logical_expr::{lit, Expr};
+use lance_core::{ROW_ADDR_FIELD, ROW_ID_FIELD};
+use lance_datafusion::planner::Planner;
+use std::collections::{HashMap, HashSet};
+use std::sync::Arc;
+
+use crate::conversion::UNIX_EPOCH_JULIAN_DAY;
+use crate::options::value_to_string;
+
+/// The table name SQL queries use for the dataset itself
+pub const SELF_TABLE: &str = "self";
+
+/// Values bound to a query's `?` placeholders, or to its `:name` placeholders
+pub enum Params {
+    Positional(Vec<ScalarValue>),
+    Named(HashMap<String, ScalarValue>),
+}
+
+fn arg_error(message: impl Into<String>) -> Error {
+    Error::new(magnus::exception::arg_error(), message.into())
+}
+
+/// Parameters from Ruby: nil for none, an Array for `?`, a Hash for `:name`
+pub fn parse_params(params: Value) -> Result<Option<Params>, Error> {
+    if params.is_nil() {
+        return Ok(None);
+    }
+
+    if let Some(named) = RHash::from_value(params) {
+        let mut values = HashMap::new();
+        named.foreach(|name: Value, value: Value| {
+            values.insert(value_to_string(name)?, scalar_value(value)?);
+            Ok(ForEach::Continue)
+        })?;
+        Ok(Some(Params::Named(values)))
+    } else {
+        let positional = RArray::try_convert(params)?
+            .into_iter()
+            .map(scalar_value)
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(Some(Params::Positional(positional)))
+    }
+}
+
+/// Convert a Ruby value to a typed SQL literal
+pub fn scalar_value(value: Value) -> Result<ScalarValue, Error> {
+    let ruby = Ruby::get().unwrap();
+
+    if value.is_nil() {
+        Ok(ScalarValue::Null)
+    } else if value.is_kind_of(ruby.class_true_class()) || value.is_kind_of(ruby.class_false_class()) {
+        Ok(ScalarValue::Boolean(Some(bool::try_convert(value)?)))
+    } else if let Some(integer) = Integer::from_value(value) {
+        Ok(ScalarValue::Int64(Some(integer.to_i64()?)))
+    } else if let Some(float) = Float::from_value(value) {
+        Ok(ScalarValue::Float64(Some(float.to_f64())))
+    } else if let Some(string) = RString::from_value(value) {
+        Ok(ScalarValue::Utf8(Some(string.to_string()?)))
+    } else if let Some(symbol) = Symbol::from_value(value) {
+        Ok(ScalarValue::Utf8(Some(symbol.name()?.to_string())))
+    } else if value.is_kind_of(ruby.class_time()) {
+        let seconds: i64 = value.funcall("to_i", ())?;
+        let micros: i64 = value.funcall("usec", ())?;
+        Ok(ScalarValue::TimestampMicrosecond(Some(seconds * 1_000_000 + micros), Some("UTC".into())))
+    } else if value.respond_to("jd", false)? {
+        // Date, or anything else with a Julian day number
+        let julian_day: i64 = value.funcall("jd", ())?;
+        let days = i32::try_from(julian_day - UNIX_EPOCH_JULIAN_DAY)
+            .map_err(|_| arg_error("Date parameter is out of range"))?;
+        Ok(ScalarValue::Date32(Some(days)))
+    } else {
+        Err(arg_error(format!("Unsupported parameter type: {}", value.class())))
+    }
+}
+
+/// Rewrite `?` and `:name` placeholders to numbered ones, `$1`, `$2`, ... for
+/// DataFusion, returning the rewritten SQL and the values in placeholder order.
+/// `placeholder` renders the placeholder for a 1-based position.
+///
+/// Placeholders inside quoted strings, quoted identifiers and comments are
+/// left alone, as are `::` casts.
+pub fn number_placeholders(
+    sql: &str,
+    params: Params,
+    placeholder: fn(usize) -> String,
+) -> Result<(String, Vec<ScalarValue>), Error> {
+    let chars: Vec<char> = sql.chars().collect();
+    let mut rewritten = String::with_capacity(sql.len());
+    let mut positional_count = 0;
+    let mut named_order: Vec<String> = Vec::new();
+    let mut i = 0;
+
+    while i < chars.len() {
+        let c = chars[i];
+        match c {
+            '\'' | '"' | '`' => {
+                // Copy through to the closing quote; doubled quotes close and reopen
+                let end = chars[i + 1..].iter().position(|&q| q == c).map_or(chars.len(), |p| i + 1 + p + 1);
+                rewritten.extend(&chars[i..end]);
+                i = end;
+            }
+            '-' if chars.get(i + 1) == Some(&'-') => {
+                let end = chars[i..].iter().position(|&n| n == '\n').map_or(chars.len(), |p| i + p);
+                rewritten.extend(&chars[i..end]);
+                i = end;
+            }
+            '/' if chars.get(i + 1) == Some(&'*') => {
+                let end = (i + 2..chars.len().saturating_sub(1))
+                    .find(|&j| chars[j] == '*' && chars[j + 1] == '/')
+                    .map_or(chars.len(), |j| j + 2);
+                rewritten.extend(&chars[i..end]);
+                i = end;
+            }
+            '?' => {
+                positional_count += 1;
+                rewritten.push_str(&placeholder(positional_count));
+                i += 1;
+            }
+            ':' if chars.get(i + 1) == Some(&':') => {
+                rewritten.push_str("::");
+                i += 2;
+            }
+            ':' if chars.get(i + 1).is_some_and(|n| n.is_ascii_alphabetic() || *n == '_') => {
+                let end = chars[i + 1..].iter()
+                    .position(|n| !(n.is_ascii_alphanumeric() || *n == '_'))
+                    .map_or(chars.len(), |p| i + 1 + p);
+                let name: String = chars[i + 1..end].iter().collect();
+                let position = match named_order.iter().position(|existing| *existing == name) {
+                    Some(position) => position,
+                    None => {
+                        named_order.push(name);
+                        named_order.len() - 1
+                    }
+                };
+                rewritten.push_str(&placeholder(position + 1));
+                i = end;
+            }
+            _ => {
+                rewritten.push(c);
+                i += 1;
+            }
+        }
+    }
+
+    let values = match params {
+        Params::Positional(values) => {
+            if !named_order.is_empty() {
+                return Err(arg_error("Named placeholders need named parameters"));
+            }
+            // Queries may also use DataFusion's own $1 placeholders
+            if positional_count > 0 && positional_count != values.len() {
+                return Err(arg_error(format!(
+                    "Expected {} parameters but got {}",
+                    positional_count,
+                    values.len()
+                )));
+            }
+            values
+        }
+        Params::Named(mut values) => {
+            if positional_count > 0 {
+                return Err(arg_error("? placeholders need positional parameters"));
+            }
+            let ordered = named_order.into_iter()
+                .map(|name| values.remove(&name).ok_or_else(|| arg_error(format!("Missing parameter: {}", name))))
+                .collect::<Result<Vec<_>, _>>()?;
+            if !values.is_empty() {
+                let mut unused: Vec<String> = values.into_keys().collect();
+                unused.sort();
+                return Err(arg_error(format!("Unused parameters: {}", unused.join(", "))));
+            }
+            ordered
+        }
+    };
+
+    Ok((rewritten, values))
+}
+
+/// DataFusion's numbered placeholder for a position
+pub fn dollar_placeholder(position: usize) -> String {
+    format!("${}", position)
+}
+
+/// Lance's filter parser has no placeholders, so filters are parsed with
+/// each placeholder standing in as a column with this prefix
+const PARAM_COLUMN_PREFIX: &str = "__lancelot_param_";
+
+fn param_column(position: usize) -> String {
+    format!("`{}{}`", PARAM_COLUMN_PREFIX, position)
+}
+
+/// Parse a filter with `?` or `:name` placeholders into an expression, with
+/// the parameters bound to the placeholders as typed literals.
+///
+/// The filter goes through Lance's own parser, as unparameterised filters do,
+/// so identifiers, `_rowid` and functions behave the same with or without
+/// parameters.
+pub fn bind_filter(filter: &str, params: Params, schema: &ArrowSchema) -> Result<Expr, Error> {
+    let (filter, values) = number_placeholders(filter, params, param_column)?;
+    let to_runtime_error = |e: lance::Error| Error::new(magnus::exception::runtime_error(), e.to_string());
+
+    // Filters may use the row id and address columns, as in Lance's scanner
+    let mut fields: Vec<ArrowField> = schema.fields().iter().map(|field| field.as_ref().clone()).collect();
+    for meta_field in [ROW_ID_FIELD.clone(), ROW_ADDR_FIELD.clone()] {
+        if schema.field_with_name(meta_field.name()).is_err() {
+            fields.push(meta_field);
+        }
+    }
+    let planner = Planner::new(Arc::new(ArrowSchema::new(fields)));
+    let expr = planner.parse_filter(&filter).map_err(to_runtime_error)?;
+
+    let mut bound = HashSet::new();
+    let expr = expr.transform(|expr| match expr {
+        Expr::Column(ref column) if column.name.starts_with(PARAM_COLUMN_PREFIX) => {
+            let position = column.name[PARAM_COLUMN_PREFIX.len()..].parse::<usize>().ok();
+            let value = position
+                .and_then(|position| values.get(position.wrapping_sub(1)))
+                .ok_or_else(|| DataFusionError::Plan(format!("No value for placeholder {}", column.name)))?;
+            bound.extend(position);
+            Ok(Transformed::yes(lit(value.clone())))
+        }
+        other => Ok(Transformed::no(other)),
+    }).map_err(|e| Error::new(magnus::exception::runtime_error(), e.to_string()))?.data;
+
+    if bound.len() != values.len() {
+        return Err(arg_error(format!("Expected {} parameters but got {}", bound.len(), values.len())));
+    }
+
+    // Cast the literals to the types of the columns they are compared with
+    planner.optimize_expr(expr).map_err(to_runtime_error)
+}
@@ -2,7 +2,9 @@
 
 require_relative "lancelot/version"
 require_relative "lancelot/lancelot"
+require_relative "lancelot/cursor"
 require_relative "lancelot/dataset"
+require_relative "lancelot/query"
 require_relative "lancelot/rank_fusion"
 
 module Lancelot
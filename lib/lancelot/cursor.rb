@@ -0,0 +1,39 @@
+# frozen_string_literal: true
+
+require 'json'
+
+module Lancelot
+  # A page of results, with the cursor for the page after it
+  class Page < Array
+    attr_reader :next_cursor
+
+    def initialize(rows, next_cursor)
+      super(rows)
+      @next_cursor = next_cursor
+    end
+  end
+
+  # Opaque pagination cursors. A cursor pins the dataset version of the first
+  # page, so later pages are read from the same version even if writers
+  # commit in between, and records the last row id and score returned so the
+  # next page can check it carries on from the same row.
+  module Cursor
+    class << self
+      def encode(version:, offset:, last_row_id:, last_score: nil)
+        payload = { v: version, o: offset, r: last_row_id, s: last_score }.compact
+        [JSON.generate(payload)].pack("m0").tr("+/", "-_").delete("=")
+      end
+
+      def decode(cursor)
+        payload = JSON.parse(cursor.to_s.tr("-_", "+/").unpack1("m"), symbolize_names: true)
+        unless payload.is_a?(Hash) && payload[:v].is_a?(Integer) && payload[:o].is_a?(Integer) && payload[:r].is_a?(Integer)
+          raise ArgumentError, "Invalid cursor"
+        end
+
+        { version: payload[:v], offset: payload[:o], last_row_id: payload[:r], last_score: payload[:s] }
+      rescue JSON::ParserError, ArgumentError
+        raise ArgumentError, "Invalid cursor"
+      end
+    end
+  end
+end
@@ -1,23 +1,36 @@
 # frozen_string_literal: true
 
+require 'date'
 require 'fileutils'
+require 'json'
 
 module Lancelot
   class Dataset
+    WRITE_OPTIONS = %i[
+      max_rows_per_file
+      max_rows_per_group
+      max_bytes_per_file
+      data_storage_version
+      enable_move_stable_row_ids
+    ].freeze
+
     class << self
-      def create(path, schema:)
+      # New datasets use move-stable row ids, so `_rowid` values survive
+      # compaction. Pass `enable_move_stable_row_ids: false` to opt out.
+      def create(path, schema:, **write_options)
+        write_options = { enable_move_stable_row_ids: true, **write_options }
         dataset = new(path)
-        dataset.create(normalize_schema(schema))
+        dataset.create(normalize_schema(schema), normalize_write_options(write_options))
         dataset
       end
 
-      def open(path)
+      def open(path, version: nil, tag: nil, as_of: nil)
         dataset = new(path)
-        dataset.open
+        dataset.open({ version: version, tag: tag&.to_s, as_of: as_of })
         dataset
       end
 
-      def open_or_create(path, schema:, mode: nil)
+      def open_or_create(path, schema:, mode: nil, **write_options)
         # Check if path exists
         if File.exist?(path)
           # Check if it's a file instead of directory
@@ -25,7 +38,7 @@ module Lancelot
             if mode == "overwrite"
               # Remove the file and create dataset
               FileUtils.rm_f(path)
-              create(path, schema: schema)
+              create(path, schema: schema, **write_options)
             else
               raise ArgumentError, "Path #{path} exists as a file, not a directory. " \
                                   "Use mode: 'overwrite' to replace it, or choose a different path."
@@ -39,7 +52,7 @@ module Lancelot
             if mode == "overwrite"
               # User explicitly wants to overwrite - remove and create new
               FileUtils.rm_rf(path)
-              create(path, schema: schema)
+              create(path, schema: schema, **write_options)
             else
               # Fail safely - don't overwrite existing non-dataset directory
               raise ArgumentError, "Directory exists at #{path} but is not a valid Lance dataset. " \
@@ -47,42 +60,83 @@ module Lancelot
             end
           else
             # Empty directory - safe to create dataset
-            create(path, schema: schema)
+            create(path, schema: schema, **write_options)
           end
         else
           # Path doesn't exist - create new dataset
-          create(path, schema: schema)
+          create(path, schema: schema, **write_options)
         end
       end
 
       private
 
       def normalize_schema(schema)
-        schema.transform_values do |type|
-          case type
-          when Hash
-            type
-          when :string, "string"
-            "string"
-          when :float, :float32, "float", "float32"
-            "float32"
-          when :float64, "float64"
-            "float64"
-          when :int, :int32, "int", "int32"
-            "int32"
-          when :int64, "int64"
-            "int64"
-          when :bool, :boolean, "bool", "boolean"
-            "boolean"
-          else
-            raise ArgumentError, "Unknown type: #{type}"
-          end
+        schema.transform_keys(&:to_sym).transform_values { |type| normalize_field(type) }
+      end
+
+      def normalize_field(type)
+        case type
+        when Hash
+          normalize_field_options(type)
+        else
+          normalize_type(type)
+        end
+      end
+
+      def normalize_field_options(options)
+        options = options.transform_keys(&:to_sym)
+        type = options.fetch(:type) { raise ArgumentError, "Field options must include a :type" }
+
+        if type.to_s == "vector"
+          options.merge(type: "vector")
+        elsif type.to_s == "list"
+          options.merge(type: "list", element_type: normalize_type(options.fetch(:element_type, :string)))
+        else
+          options.merge(type: normalize_type(type))
         end
       end
+
+      def normalize_type(type)
+        case type
+        when :string, "string"
+          "string"
+        when :float, :float32, "float", "float32"
+          "float32"
+        when :float64, "float64"
+          "float64"
+        when :int, :int32, "int", "int32"
+          "int32"
+        when :int64, "int64"
+          "int64"
+        when :bool, :boolean, "bool", "boolean"
+          "boolean"
+        when :date, "date"
+          "date"
+        else
+          raise ArgumentError, "Unknown type: #{type}"
+        end
+      end
+
+      def normalize_write_options(options)
+        unknown = options.keys - WRITE_OPTIONS
+        unless unknown.empty?
+          raise ArgumentError, "Unknown write options: #{unknown.join(", ")}"
+        end
+
+        options
+      end
     end
 
-    def add_documents(documents)
-      add_data(documents.map { |doc| normalize_document(doc) })
+    # When set to a row count, appends that leave any index with more than
+    # that many unindexed rows optimize the stale indexes afterwards. The
+    # setting is stored in the dataset, so it applies to every handle on it
+    # and survives reopening; set it to nil to turn it off.
+    def auto_optimize_indexes=(threshold)
+      _rust_set_auto_optimize_indexes(threshold&.to_i)
+    end
+
+    def add_documents(documents, **write_options)
+      add_data(documents.map { |doc| normalize_document(doc) }, self.class.send(:normalize_write_options, write_options))
     end
 
     def <<(document)
@@ -105,89 +159,490 @@ module Lancelot
       end
     end
 
-    def all
-      scan_all
+    SORT_DIRECTIONS = %w[asc desc].freeze
+
+    # Every read method accepts `select:` to read only the listed columns, or
+    # `exclude:` to skip some, such as large vector columns, and
+    # `with_row_id: true` to return each row's `_rowid` for use with `take`
+    #
+    # `scan_limit`, `where` and `vector_search` accept `explain: true` to return
+    # the physical plan Lance would run, as a string, instead of the rows.
+    #
+    # `scan_all`, `scan_limit` and `where` also accept `order_by:`, such as
+    # `order_by: [[:published_at, :desc], [:id, :asc]]`. Sorting happens in the
+    # scanner, which keeps only the top rows when there is a limit.
+    def scan_all(select: nil, exclude: nil, with_row_id: false, order_by: nil)
+      _rust_scan_all({ **projection(select, exclude, with_row_id), order_by: normalize_order_by(order_by) })
     end
 
-    def first(n = nil)
+    # `offset:` skips that many rows. `cursor: true` instead returns a
+    # Lancelot::Page whose `next_cursor` fetches the page after it, read from
+    # the same version of the dataset; the same goes for `where`,
+    # `vector_search` and `text_search`. Cursors page by offset, so each page
+    # re-reads the rows before it, and raise ArgumentError if the row before
+    # the page is not the one the cursor last returned.
+    def scan_limit(limit, offset: nil, cursor: nil, select: nil, exclude: nil, with_row_id: false, order_by: nil,
+                   explain: false)
+      order_by = normalize_order_by(order_by)
+      paginate(limit, offset, cursor, nil, with_row_id, explain: explain) do |page_limit, read_options|
+        _rust_scan_limit(page_limit, { **projection(select, exclude, with_row_id), order_by: order_by, **read_options })
+      end
+    end
+
+    def all(select: nil, exclude: nil, with_row_id: false)
+      scan_all(select: select, exclude: exclude, with_row_id: with_row_id)
+    end
+
+    def first(n = nil, select: nil, exclude: nil, with_row_id: false)
       if n.nil?
-        scan_limit(1).first
+        scan_limit(1, select: select, exclude: exclude, with_row_id: with_row_id).first
       else
-        scan_limit(n)
+        scan_limit(n, select: select, exclude: exclude, with_row_id: with_row_id)
       end
     end
 
-    def each(&block)
-      return enum_for(:each) unless block_given?
-      scan_all.each(&block)
+    def each(select: nil, exclude: nil, with_row_id: false, &block)
+      return enum_for(:each, select: select, exclude: exclude, with_row_id: with_row_id) unless block_given?
+      scan_all(select: select, exclude: exclude, with_row_id: with_row_id).each(&block)
+    end
+
+    # Rows by the `_rowid` returned with `with_row_id: true`, in the order
+    # given. Row ids of datasets created with move-stable row ids survive
+    # compaction.
+    def take(row_ids, columns: nil, as_of: nil)
+      _rust_take_rows(Array(row_ids), { as_of: as_of, **projection(columns, nil) })
+    end
+
+    # Rows by their position in the dataset, in the order given
+    def take_offsets(indices, columns: nil, as_of: nil)
+      _rust_take(Array(indices), { as_of: as_of, **projection(columns, nil) })
     end
 
     include Enumerable
 
-    def vector_search(query_vector, column: "vector", limit: 10)
+    VECTOR_INDEX_TYPES = %w[ivf_flat ivf_pq ivf_sq ivf_hnsw_sq ivf_hnsw_pq].freeze
+
+    # Build a vector index. Parameters not given are derived from the row
+    # count and vector dimension.
+    def create_vector_index(column, type: :ivf_flat, metric: nil, num_partitions: nil, num_sub_vectors: nil,
+                            num_bits: nil, m: nil, ef_construction: nil, sample_rate: nil, max_iterations: nil,
+                            name: nil, replace: true)
+      type = type.to_s.downcase
+      unless VECTOR_INDEX_TYPES.include?(type)
+        raise ArgumentError, "Unknown vector index type: #{type}. Expected one of #{VECTOR_INDEX_TYPES.join(", ")}"
+      end
+
+      _rust_create_vector_index(column.to_s, {
+        type: type,
+        metric: metric&.to_s,
+        num_partitions: num_partitions,
+        num_sub_vectors: num_sub_vectors,
+        num_bits: num_bits,
+        m: m,
+        ef_construction: ef_construction,
+        sample_rate: sample_rate,
+        max_iterations: max_iterations,
+        name: name&.to_s,
+        replace: replace
+      })
+    end
+
+    SCALAR_INDEX_TYPES = %w[btree bitmap label_list].freeze
+
+    # Build parameters Lance records in vector index statistics; text indexes
+    # record theirs under `params`, and scalar indexes record none
+    VECTOR_INDEX_PARAM_KEYS = %i[metric_type num_partitions sub_index].freeze
+
+    # Build a scalar index so filters on the column can skip a full scan.
+    # Use :btree for high-cardinality or range-filtered columns, :bitmap for
+    # columns with few distinct values, and :label_list for list columns
+    # filtered with array_has_any/array_has_all.
+    def create_scalar_index(column, type: :btree, name: nil, replace: true)
+      type = type.to_s.downcase
+      unless SCALAR_INDEX_TYPES.include?(type)
+        raise ArgumentError, "Unknown scalar index type: #{type}. Expected one of #{SCALAR_INDEX_TYPES.join(", ")}"
+      end
+
+      _rust_create_scalar_index(column.to_s, { type: type, name: name&.to_s, replace: replace })
+    end
+
+    # Build an inverted index for full-text search. `base_tokenizer` is one of
+    # :simple (the default), :whitespace, :raw, :ngram, :jieba or :lindera,
+    # the last two needing a language model. `language` picks the stemmer and
+    # stop word list. Phrase queries need `with_position: true`.
+    def create_text_index(column, name: nil, replace: true, base_tokenizer: nil, language: nil, stem: nil,
+                          remove_stop_words: nil, ascii_folding: nil, lower_case: nil, max_token_length: nil,
+                          with_position: nil)
+      _rust_create_text_index(column.to_s, {
+        name: name&.to_s,
+        replace: replace,
+        base_tokenizer: base_tokenizer&.to_s,
+        language: language&.to_s,
+        stem: stem,
+        remove_stop_words: remove_stop_words,
+        ascii_folding: ascii_folding,
+        lower_case: lower_case,
+        max_token_length: max_token_length,
+        with_position: with_position
+      })
+    end
+
+    # Indexes on the dataset. Unnamed indexes are called "<column>_idx".
+    # Rows added since an index was built count as unindexed until the index
+    # is rebuilt.
+    def indexes
+      _rust_indexes.map do |index|
+        stats = index_stats(index[:name])
+        details = Array(stats[:indices]).first || {}
+
+        {
+          name: index[:name],
+          type: index_type_name(stats[:index_type]),
+          columns: index[:columns],
+          params: index_params(details),
+          num_indexed_rows: stats[:num_indexed_rows],
+          num_unindexed_rows: stats[:num_unindexed_rows],
+          size_bytes: index[:uuids].sum { |uuid| path_size(File.join(path, "_indices", uuid)) }
+        }
+      end
+    end
+
+    # Lance's full statistics for the named index
+    def index_stats(name)
+      JSON.parse(_rust_index_stats(name.to_s), symbolize_names: true)
+    end
+
+    def drop_index(name)
+      _rust_drop_index(name.to_s)
+      self
+    end
+
+    def rename_index(from, to)
+      _rust_rename_index(from.to_s, to.to_s)
+      self
+    end
+
+    # Bring indexes up to date with rows added since they were built, without
+    # rebuilding them from scratch. New rows go into a delta index, merged with
+    # the latest `num_indices_to_merge` existing deltas. `retrain: true`
+    # rebuilds vector indexes, retraining IVF centroids and PQ codebooks.
+    def optimize_indexes(columns: nil, num_indices_to_merge: nil, retrain: false)
+      index_names = nil
+      if columns
+        columns = Array(columns).map(&:to_s)
+        index_names = _rust_indexes.select { |index| (index[:columns] & columns).any? }.map { |index| index[:name] }
+        raise ArgumentError, "No indexes on columns: #{columns.join(", ")}" if index_names.empty?
+      end
+
+      _rust_optimize_indexes({ index_names: index_names, num_indices_to_merge: num_indices_to_merge, retrain: retrain })
+      self
+    end
+
+    # Brute-force searches and index searches use `metric`, defaulting to the
+    # metric recorded for the column in the schema, then to L2.
+    #
+    # Index searches can be tuned per query: `nprobes` is the number of IVF
+    # partitions to probe, or `minimum_nprobes` and `maximum_nprobes` bound the
+    # number Lance probes until it finds enough results, `refine_factor` re-ranks `limit * refine_factor`
+    # candidates with exact distances, and `ef` sets the HNSW search breadth.
+    # `use_index: false` forces an exact brute-force search, and
+    # `fast_search: true` skips rows appended since the index was built.
+    #
+    # `filter` takes the same SQL syntax as `where`, with parameters given as
+    # `["author = ? AND year > ?", name, 2020]`. Filtering happens before the
+    # search unless `prefilter: false`, so up to `limit` rows still match.
+    def vector_search(query_vector, column: "vector", limit: 10, metric: nil, as_of: nil,
+                      nprobes: nil, refine_factor: nil, ef: nil, use_index: nil, fast_search: nil,
+                      filter: nil, prefilter: nil, select: nil, exclude: nil, with_row_id: false,
+                      offset: nil, cursor: nil, minimum_nprobes: nil, maximum_nprobes: nil, explain: false)
       unless query_vector.is_a?(Array)
         raise ArgumentError, "Query vector must be an array of numbers"
       end
       
-      _rust_vector_search(column.to_s, query_vector, limit)
+      paginate(limit, offset, cursor, as_of, with_row_id, :_distance, explain: explain) do |page_limit, read_options|
+        _rust_vector_search(column.to_s, query_vector, page_limit, {
+          metric: metric&.to_s,
+          nprobes: nprobes,
+          minimum_nprobes: minimum_nprobes,
+          maximum_nprobes: maximum_nprobes,
+          refine_factor: refine_factor,
+          ef: ef,
+          use_index: use_index,
+          fast_search: fast_search,
+          prefilter: prefilter,
+          **filter_options(filter),
+          **projection(select, exclude, with_row_id),
+          **read_options
+        })
+      end
     end
 
-    def nearest_neighbors(vector, k: 10, column: "vector")
-      vector_search(vector, column: column, limit: k)
+    def nearest_neighbors(vector, k: 10, column: "vector", **search_options)
+      vector_search(vector, column: column, limit: k, **search_options)
     end
 
-    def text_search(query, column: nil, columns: nil, limit: 10)
-      unless query.is_a?(String)
-        raise ArgumentError, "Query must be a string"
+    def text_search(query, column: nil, columns: nil, limit: 10, as_of: nil, filter: nil, prefilter: nil,
+                    select: nil, exclude: nil, with_row_id: false, offset: nil, cursor: nil)
+      unless query.is_a?(String) || query.is_a?(Hash)
+        raise ArgumentError, "Query must be a string or a Lancelot::Query"
       end
       
-      if column && columns
+      if query.is_a?(Hash) && (column || columns)
+        # Structured queries name their own columns
+        raise ArgumentError, "Cannot specify column or columns with a structured query"
+      elsif column && columns
         raise ArgumentError, "Cannot specify both column and columns"
-      elsif columns
-        # Multi-column search
-        columns = Array(columns).map(&:to_s)
-        _rust_multi_column_text_search(columns, query, limit)
-      else
-        # Single column search (default to "text" if not specified)
-        column ||= "text"
-        _rust_text_search(column.to_s, query, limit)
+      end
+      
+      paginate(limit, offset, cursor, as_of, with_row_id, :_score) do |page_limit, read_options|
+        options = { prefilter: prefilter, **filter_options(filter), **projection(select, exclude, with_row_id), **read_options }
+        
+        if query.is_a?(Hash)
+          _rust_query_text_search(query, page_limit, options)
+        elsif columns
+          # Multi-column search, with per-column boosts such as "title^3"
+          columns = Array(columns).map(&:to_s)
+          if columns.any? { |name| name.include?("^") }
+            _rust_query_text_search(Lancelot::Query.multi_match(query, columns: columns), page_limit, options)
+          else
+            _rust_multi_column_text_search(columns, query, page_limit, options)
+          end
+        else
+          # Single column search (default to "text" if not specified)
+          _rust_text_search((column || "text").to_s, query, page_limit, options)
+        end
       end
     end
 
-    def hybrid_search(query, vector_column: "vector", text_column: nil, text_columns: nil, 
-                      vector: nil, limit: 10, rrf_k: 60)
-      require 'lancelot/rank_fusion'
+    # Vector and full-text search fused by row id. `fusion:` is :rrf (weighted
+    # reciprocal rank fusion), :linear (weighted sum of normalised scores) or
+    # :distance (candidates from both searches ordered by vector distance).
+    def hybrid_search(query, vector_column: "vector", text_column: nil, text_columns: nil,
+                      vector: nil, limit: 10, fusion: :rrf, rrf_k: 60, weights: nil,
+                      normalization: :minmax, candidates: nil, as_of: nil, filter: nil,
+                      prefilter: nil, select: nil, exclude: nil, with_row_id: false)
+      if vector && !vector.is_a?(Array)
+        raise ArgumentError, "Vector must be an array of numbers"
+      end
       
-      result_lists = []
+      search_options = { as_of: as_of, filter: filter, prefilter: prefilter, select: select, exclude: exclude,
+                         with_row_id: with_row_id }
+      has_query = query && !query.empty?
       
-      # Perform vector search if vector is provided
-      if vector
-        unless vector.is_a?(Array)
-          raise ArgumentError, "Vector must be an array of numbers"
+      # With only one search there is nothing to fuse
+      return [] unless vector || has_query
+      unless vector && has_query
+        if vector
+          return vector_search(vector, column: vector_column, limit: limit, **search_options)
+        else
+          return text_search(query, column: text_column, columns: text_columns, limit: limit, **search_options)
         end
-        
-        vector_results = vector_search(vector, column: vector_column, limit: limit * 2)
-        result_lists << vector_results if vector_results.any?
       end
       
-      # Perform text search if query is provided
-      if query && !query.empty?
-        text_results = text_search(query, column: text_column, columns: text_columns, limit: limit * 2)
-        result_lists << text_results if text_results.any?
+      if query.is_a?(Hash) && (text_column || text_columns)
+        raise ArgumentError, "Cannot specify text_column or text_columns with a structured query"
+      elsif text_column && text_columns
+        raise ArgumentError, "Cannot specify both text_column and text_columns"
       end
       
-      # Return empty array if no searches were performed
-      return [] if result_lists.empty?
+      unless query.is_a?(Hash)
+        columns = Array(text_columns || text_column || "text").map(&:to_s)
+        # Per-column boosts such as "title^3" need a structured query
+        query = Lancelot::Query.multi_match(query, columns: columns) if columns.any? { |name| name.include?("^") }
+      end
       
-      # Return single result list if only one search was performed
-      return result_lists.first[0...limit] if result_lists.size == 1
+      weights = (weights || {}).transform_keys(&:to_sym)
+      options = {
+        vector_column: vector_column.to_s,
+        text_columns: query.is_a?(Hash) ? nil : columns,
+        fusion: fusion.to_s,
+        rrf_k: rrf_k.to_f,
+        normalization: normalization.to_s,
+        vector_weight: weights.fetch(:vector, 1.0).to_f,
+        text_weight: weights.fetch(:text, 1.0).to_f,
+        candidates: candidates || limit * 2,
+        as_of: as_of,
+        prefilter: prefilter,
+        **filter_options(filter),
+        **projection(select, exclude, with_row_id)
+      }
       
-      # Perform RRF fusion and limit results
-      Lancelot::RankFusion.reciprocal_rank_fusion(result_lists, k: rrf_k)[0...limit]
+      _rust_hybrid_search(vector, query, limit, options)
     end
 
-    def where(filter_expression, limit: nil)
-      filter_scan(filter_expression.to_s, limit)
+    # Values for `?` placeholders follow the filter
+    # (`where("author = ? AND year > ?", name, 2020)`), and values for `:name`
+    # placeholders are given as a hash (`where("author = :author", { author: name })`).
+    # They are bound as typed literals rather than interpolated into the filter.
+    def where(filter_expression, *params, limit: nil, offset: nil, cursor: nil, as_of: nil, select: nil,
+              exclude: nil, with_row_id: false, order_by: nil, explain: false)
+      order_by = normalize_order_by(order_by)
+      paginate(limit, offset, cursor, as_of, with_row_id, explain: explain) do |page_limit, read_options|
+        _rust_filter_scan(filter_expression.to_s, page_limit, {
+          filter_params: bind_params(params),
+          **projection(select, exclude, with_row_id),
+          order_by: order_by,
+          **read_options
+        })
+      end
+    end
+
+    def filter_scan(filter_expression, limit = nil, params: nil, select: nil, exclude: nil, with_row_id: false,
+                    order_by: nil)
+      _rust_filter_scan(filter_expression, limit, {
+        filter_params: params,
+        **projection(select, exclude, with_row_id),
+        order_by: normalize_order_by(order_by)
+      })
+    end
+
+    # Run a read-only SQL query through DataFusion. The dataset is the table
+    # `self`, and other datasets can be joined by passing
+    # `tables: { authors: authors_dataset }`. Values are bound to `?`
+    # placeholders (`sql(query, "ruby", 3)`) or to `:name` placeholders from a
+    # hash (`sql(query, { name: "ruby" })`) as typed literals, never
+    # interpolated into the SQL.
+    def sql(query, *params, as_of: nil, tables: nil)
+      _rust_sql(query.to_s, bind_params(params), { as_of: as_of, tables: tables })
+    end
+
+    # Add columns computed from SQL expressions (`add_columns(doubled: "score * 2")`)
+    # or all-null columns from a schema (`add_columns(schema: { language: :string })`)
+    def add_columns(expressions = nil, schema: nil)
+      if expressions && schema
+        raise ArgumentError, "Cannot specify both expressions and schema"
+      elsif schema
+        _rust_add_null_columns(self.class.send(:normalize_schema, schema))
+      elsif expressions
+        _rust_add_columns(expressions.to_h { |name, expression| [name.to_sym, expression.to_s] })
+      else
+        raise ArgumentError, "Either expressions or schema must be provided"
+      end
+
+      self
+    end
+
+    def drop_columns(*columns)
+      _rust_drop_columns(columns.flatten.map(&:to_s))
+      self
+    end
+
+    # Alter existing columns, e.g. `alter_columns(year: { cast: :int64 }, title: { rename: "headline" })`
+    def alter_columns(alterations)
+      normalized = alterations.to_h do |column, options|
+        options = options.transform_keys(&:to_sym)
+        options[:rename] = options[:rename].to_s if options.key?(:rename)
+        options[:cast] = self.class.send(:normalize_field, options[:cast]) if options.key?(:cast)
+        [column.to_sym, options]
+      end
+
+      _rust_alter_columns(normalized)
+      self
+    end
+
+    def rename_column(from, to)
+      alter_columns(from => { rename: to })
+    end
+
+    # Backfill a new column by calling the block with batches of existing documents.
+    # The block must return one value per document. Results are checkpointed so an
+    # interrupted backfill resumes where it stopped; pass `checkpoint: false` to disable.
+    def add_column(name, type:, columns: nil, batch_size: nil, checkpoint: nil, &block)
+      raise ArgumentError, "add_column requires a block" unless block
+
+      checkpoint = File.join(path, "_lancelot_checkpoints", name.to_s) if checkpoint.nil?
+      options = {
+        columns: columns&.map(&:to_s),
+        batch_size: batch_size,
+        checkpoint: checkpoint ? checkpoint.to_s : nil
+      }
+
+      _rust_add_column_with(name.to_s, self.class.send(:normalize_field, type), options, block)
+      self
+    end
+
+    # Every commit creates a version. Returns them oldest first with their
+    # timestamp, the operation that created them and the row count.
+    def versions
+      _rust_versions.map do |info|
+        micros = info.delete(:timestamp_micros)
+        info.merge(timestamp: Time.at(Rational(micros, 1_000_000)))
+      end
+    end
+
+    # The fragments of the current version, each with its id, row count and
+    # data file paths
+    def fragments
+      _rust_fragments
+    end
+
+    # Rows inserted, deleted and updated between two versions. Rows are paired
+    # up by the `key` column to detect updates; pass `key: nil` to report only
+    # inserts and deletes. With a block, yields each change type and document.
+    def diff(from_version, to_version = version, key: :id)
+      key = key&.to_sym
+      if key && !checkout(to_version).schema.key?(key)
+        raise ArgumentError, "Key column #{key} not found; pass key: nil to diff without a key"
+      end
+
+      changes = diff_changes(_rust_diff(from_version, to_version), key)
+      return changes unless block_given?
+
+      changes.each do |type, documents|
+        documents.each { |doc| yield type, doc }
+      end
+    end
+
+    # Tag a version (the current one by default) so it can be opened by name.
+    # Tagged versions are never removed by version cleanup.
+    def tag(name, version = self.version)
+      _rust_create_tag(name.to_s, version)
+      self
+    end
+
+    # Returns a hash of tag name => version number
+    def tags
+      _rust_tags.sort_by { |_name, version| version }.to_h
+    end
+
+    def delete_tag(name)
+      _rust_delete_tag(name.to_s)
+      self
+    end
+
+    # Remove versions older than `older_than` (seconds, or a Time cutoff) and the
+    # files only they reference. The latest version and tagged versions are never
+    # removed; with `error_if_tagged: true`, finding an old tagged version raises
+    # instead of keeping it. Returns the bytes, versions and files removed. With
+    # `dry_run: true`, nothing is deleted and the same keys describe what would be.
+    def cleanup(older_than:, error_if_tagged: false, delete_unverified: false, dry_run: false)
+      older_than_seconds = older_than.is_a?(Time) ? Time.now - older_than : older_than.to_f
+      options = { error_if_tagged: error_if_tagged, delete_unverified: delete_unverified }
+
+      if dry_run
+        plan = _rust_cleanup_plan(older_than_seconds, options)
+
+        return {
+          bytes_removed: plan[:files].sum { |file| file[:size] },
+          versions_removed: plan[:versions].size,
+          files: plan[:files]
+        }
+      end
+
+      _rust_cleanup(older_than_seconds, options)
+    end
+
+    # Merge small fragments into larger ones. Existing indexes are remapped to
+    # the rewritten fragments so they stay valid.
+    def compact(target_rows_per_fragment: nil, max_rows_per_group: nil, materialize_deletions: nil,
+                materialize_deletions_threshold: nil, num_threads: nil)
+      _rust_compact({
+        target_rows_per_fragment: target_rows_per_fragment,
+        max_rows_per_group: max_rows_per_group,
+        materialize_deletions: materialize_deletions,
+        materialize_deletions_threshold: materialize_deletions_threshold,
+        num_threads: num_threads
+      })
     end
 
     def to_s
@@ -206,8 +661,135 @@ module Lancelot
 
     private
 
+    # "IVF_PQ" => "ivf_pq", "LabelList" => "label_list", "BTree" => "btree"
+    def index_params(details)
+      details[:params] || details.slice(*VECTOR_INDEX_PARAM_KEYS)
+    end
+
+    def index_type_name(type)
+      type&.gsub(/([a-z])([A-Z])/, '\1_\2')&.downcase
+    end
+
+    def projection(select, exclude, with_row_id = false)
+      {
+        select: select && Array(select).map(&:to_s),
+        exclude: exclude && Array(exclude).map(&:to_s),
+        with_row_id: with_row_id
+      }
+    end
+
+    # Parameters for `?` placeholders, or for `:name` placeholders given as a
+    # single Hash
+    def bind_params(params)
+      if params.size == 1 && params.first.is_a?(Hash)
+        params.first
+      elsif params.any?
+        params
+      end
+    end
+
+    # The `filter` and `filter_params` options for a filter given as a string,
+    # or as an array of the filter and its parameters
+    def filter_options(filter)
+      return { filter: filter } unless filter.is_a?(Array)
+
+      expression, *params = filter
+      { filter: expression, filter_params: bind_params(params) }
+    end
+
+    # `order_by` as [column, direction] pairs. Accepts a column, a
+    # `[column, direction]` pair, a `{ column => direction }` hash, or an
+    # array of any of these.
+    def normalize_order_by(order_by)
+      return nil if order_by.nil?
+
+      terms = if order_by.is_a?(Array) && order_by.size == 2 && SORT_DIRECTIONS.include?(order_by.last.to_s.downcase)
+        [order_by]
+      else
+        Array(order_by.is_a?(Hash) ? order_by.to_a : order_by)
+      end
+
+      terms.flat_map { |term| term.is_a?(Hash) ? term.to_a : [term] }.map do |column, direction = :asc|
+        direction = direction.to_s.downcase
+        raise ArgumentError, "Unknown sort direction: #{direction}" unless SORT_DIRECTIONS.include?(direction)
+
+        [column.to_s, direction]
+      end
+    end
+
+    # Run a read at `offset`, or page through it with `cursor`. Each page after
+    # the first re-reads the last row of the previous one to check that the
+    # pages line up.
+    def paginate(limit, offset, cursor, as_of, with_row_id, score_key = nil, explain: false)
+      unless cursor
+        return yield(limit, { offset: offset, as_of: as_of, with_row_id: with_row_id, explain: explain })
+      end
+
+      raise ArgumentError, "Cannot specify cursor with explain" if explain
+
+      raise ArgumentError, "A limit is required with cursor" unless limit
+      raise ArgumentError, "Cannot specify cursor with offset or as_of" if offset || as_of
+
+      position = cursor == true ? { version: version, offset: 0 } : Cursor.decode(cursor)
+      overlap = position[:offset].positive? ? 1 : 0
+      rows = yield(limit + overlap, {
+        offset: position[:offset] - overlap,
+        version: position[:version],
+        with_row_id: true
+      })
+
+      if overlap == 1
+        previous = rows.shift
+        unless previous && previous[:_rowid] == position[:last_row_id] &&
+               (position[:last_score].nil? || previous[score_key] == position[:last_score])
+          raise ArgumentError, "Cursor does not match version #{position[:version]} of the dataset"
+        end
+      end
+
+      next_cursor = if limit.positive? && rows.size == limit
+        Cursor.encode(
+          version: position[:version],
+          offset: position[:offset] + rows.size,
+          last_row_id: rows.last[:_rowid],
+          last_score: score_key && rows.last[score_key]
+        )
+      end
+      rows.each { |row| row.delete(:_rowid) } unless with_row_id
+
+      Page.new(rows, next_cursor)
+    end
+
     def normalize_document(doc)
       doc.transform_keys(&:to_sym)
     end
+
+    def path_size(file)
+      if File.directory?(file)
+        Dir.glob("**/*", base: file).sum { |entry| File.size?(File.join(file, entry)).to_i }
+      else
+        File.size?(file).to_i
+      end
+    end
+
+    def diff_changes(changes, key)
+      removed, added = changes.values_at(:removed, :added)
+      return { inserted: added, deleted: removed, updated: [] } unless key
+
+      removed_by_key = removed.group_by { |doc| doc[key] }
+      added_by_key = added.group_by { |doc| doc[key] }
+
+      # Rows that were deleted and re-added with the same key are updates,
+      # unless only their storage changed (e.g. after compaction)
+      updated = added.select do |doc|
+        previous = removed_by_key[doc[key]]
+        !doc[key].nil? && previous && !previous.include?(doc)
+      end
+
+      {
+        inserted: added.reject { |doc| !doc[key].nil? && removed_by_key.key?(doc[key]) },
+        deleted: removed.reject { |doc| !doc[key].nil? && added_by_key.key?(doc[key]) },
+        updated: updated
+      }
+    end
   end
 end
\ No newline at end of file
@@ -0,0 +1,90 @@
+# frozen_string_literal: true
+
+module Lancelot
+  # Builders for structured full-text queries, passed to `text_search` in
+  # place of a query string:
+  #
+  #   query = Lancelot::Query.boolean(
+  #     must: [Lancelot::Query.phrase("machine learning", column: "body", slop: 1)],
+  #     should: [Lancelot::Query.match("ruby", column: "title", boost: 2.0)],
+  #     must_not: [Lancelot::Query.match("python", column: "body")]
+  #   )
+  #   dataset.text_search(query)
+  #
+  # Queries are plain hashes, so they can be stored and composed freely.
+  module Query
+    class << self
+      # Documents containing the terms. `operator: :and` requires every term,
+      # and `fuzziness` allows that many edits per term (:auto picks by length).
+      def match(terms, column: nil, boost: nil, fuzziness: nil, max_expansions: nil, prefix_length: nil,
+                operator: nil)
+        {
+          type: "match",
+          terms: terms.to_s,
+          column: column&.to_s,
+          boost: boost,
+          fuzziness: fuzziness,
+          max_expansions: max_expansions,
+          prefix_length: prefix_length,
+          operator: operator&.to_s
+        }.compact
+      end
+
+      def fuzzy(terms, distance: :auto, column: nil, **options)
+        match(terms, column: column, fuzziness: distance, **options)
+      end
+
+      # Documents containing the terms in order, with up to `slop` other
+      # words between them. The column's index needs `with_position: true`.
+      def phrase(terms, column: nil, slop: nil)
+        { type: "phrase", terms: terms.to_s, column: column&.to_s, slop: slop }.compact
+      end
+
+      # Match the terms in several columns. Columns may carry a boost, as in
+      # `columns: ["title^3", "body"]`.
+      def multi_match(terms, columns:, operator: nil)
+        raise ArgumentError, "multi_match needs at least one column" if Array(columns).empty?
+
+        columns, boosts = Array(columns).map { |column| parse_boosted_column(column) }.transpose
+
+        {
+          type: "multi_match",
+          terms: terms.to_s,
+          columns: columns,
+          boosts: (boosts if boosts.any? { |boost| boost != 1.0 }),
+          operator: operator&.to_s
+        }.compact
+      end
+
+      # Combine queries. Documents must match every `must` query and no
+      # `must_not` query; `should` queries raise the score, and are required
+      # when there are no `must` queries or `minimum_should_match` is given.
+      def boolean(must: [], should: [], must_not: [], minimum_should_match: nil)
+        if must.empty? && should.empty?
+          raise ArgumentError, "Boolean queries need at least one must or should clause"
+        end
+
+        {
+          type: "boolean",
+          must: must,
+          should: should,
+          must_not: must_not,
+          minimum_should_match: minimum_should_match
+        }.compact
+      end
+
+      # Rank documents matching `negative` lower, scaling their scores by `negative_boost`
+      def boost(positive, negative:, negative_boost: nil)
+        { type: "boost", positive: positive, negative: negative, negative_boost: negative_boost }.compact
+      end
+
+      private
+
+      # Split "title^3" into the column name and its boost
+      def parse_boosted_column(column)
+        name, boost = column.to_s.split("^", 2)
+        [name, boost ? Float(boost) : 1.0]
+      end
+    end
+  end
+end
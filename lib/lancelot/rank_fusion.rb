@@ -45,8 +45,9 @@ module Lancelot
 
         result_lists.each_with_index do |list, list_idx|
           list.each_with_index do |doc, rank|
-            # Use the document content as the key (excluding metadata like distance/score)
-            doc_key = normalize_document(doc)
+            # Results read with `with_row_id: true` are matched by row id, others
+            # by their content (excluding metadata like distance/score)
+            doc_key = doc.key?(:_rowid) ? doc[:_rowid] : normalize_document(doc)
             doc_ranks[doc_key] ||= {document: doc, ranks: {}}
             doc_ranks[doc_key][:ranks][list_idx] = rank + 1  # 1-based ranking
           end
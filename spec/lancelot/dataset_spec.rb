@@ -36,6 +36,69 @@ RSpec.describe Lancelot::Dataset do
         score: "float32"
       })
     end
+
+    it "accepts write options" do
+      schema = { text: :string, score: :float32 }
+
+      dataset = Lancelot::Dataset.create(dataset_path, schema: schema,
+                                         max_rows_per_file: 2,
+                                         max_rows_per_group: 2,
+                                         data_storage_version: "stable")
+      dataset.add_documents([
+        { text: "one", score: 0.1 },
+        { text: "two", score: 0.2 },
+        { text: "three", score: 0.3 }
+      ])
+
+      expect(dataset.count).to eq(3)
+      expect(dataset.fragments.map { |fragment| fragment[:num_rows] }).to eq([2, 1])
+      expect(dataset.fragments.map { |fragment| fragment[:files].size }).to eq([1, 1])
+    end
+
+    it "applies the layout options given to create to later appends" do
+      Lancelot::Dataset.create(dataset_path, schema: { text: :string }, max_rows_per_file: 2)
+
+      reopened = Lancelot::Dataset.open(dataset_path)
+      reopened.add_documents([{ text: "one" }, { text: "two" }, { text: "three" }])
+      expect(reopened.fragments.map { |fragment| fragment[:num_rows] }).to eq([2, 1])
+
+      reopened.add_documents([{ text: "four" }, { text: "five" }, { text: "six" }], max_rows_per_file: 3)
+      expect(reopened.fragments.map { |fragment| fragment[:num_rows] }).to eq([2, 1, 3])
+    end
+
+    it "keeps row groups inside a single file" do
+      dataset = Lancelot::Dataset.create(dataset_path, schema: { text: :string }, max_rows_per_group: 1)
+      dataset.add_documents([{ text: "one" }, { text: "two" }, { text: "three" }])
+
+      expect(dataset.fragments.size).to eq(1)
+      expect(dataset.fragments.first[:num_rows]).to eq(3)
+      expect(dataset.fragments.first[:files].size).to eq(1)
+    end
+
+    it "accepts per-column compression hints" do
+      schema = {
+        text: { type: :string, compression: :zstd, compression_level: 3 },
+        embedding: { type: "vector", dimension: 3, compression: "lz4" }
+      }
+
+      dataset = Lancelot::Dataset.create(dataset_path, schema: schema)
+      dataset.add_documents([{ text: "hello", embedding: [0.1, 0.2, 0.3] }])
+
+      expect(dataset.schema[:text]).to eq("string")
+      expect(dataset.first[:text]).to eq("hello")
+    end
+
+    it "raises an error for unknown write options" do
+      expect {
+        Lancelot::Dataset.create(dataset_path, schema: { text: :string }, rows_per_file: 10)
+      }.to raise_error(ArgumentError, /Unknown write options: rows_per_file/)
+    end
+
+    it "raises an error for an invalid storage version" do
+      expect {
+        Lancelot::Dataset.create(dataset_path, schema: { text: :string }, data_storage_version: "9.9")
+      }.to raise_error(ArgumentError)
+    end
   end
 
   describe ".open" do
@@ -231,6 +294,18 @@ RSpec.describe Lancelot::Dataset do
       expect(dataset.count).to eq(1)
     end
 
+    it "accepts write options on append" do
+      documents = (1..5).map { |i| { text: "doc #{i}", score: i / 10.0, embedding: [0.1, 0.2, 0.3] } }
+
+      fragments_before = dataset.fragments.size
+      dataset.add_documents(documents, max_rows_per_file: 2, max_rows_per_group: 1)
+      expect(dataset.count).to eq(5)
+
+      appended = dataset.fragments.drop(fragments_before)
+      expect(appended.map { |fragment| fragment[:num_rows] }).to eq([2, 2, 1])
+      expect(appended.sum { |fragment| fragment[:files].size }).to eq(3)
+    end
+
     context "with optional fields (schema evolution)" do
       it "allows adding documents with missing fields that were added later" do
         # This test verifies the fix for optional fields in conversion.rs
@@ -396,6 +471,20 @@ RSpec.describe Lancelot::Dataset do
       expect(returned_schema[:small_vector][:dimension]).to eq(3)
     end
     
+    it "returns correct schema for date and list columns" do
+      schema = {
+        published_on: :date,
+        tags: { type: "list" },
+        ratings: { type: "list", element_type: :int64 }
+      }
+
+      dataset = Lancelot::Dataset.create(dataset_path, schema: schema)
+
+      expect(dataset.schema[:published_on]).to eq("date")
+      expect(dataset.schema[:tags]).to eq({ type: "list", element_type: "string" })
+      expect(dataset.schema[:ratings]).to eq({ type: "list", element_type: "int64" })
+    end
+
     it "returns correct schema after reopening dataset" do
       schema = {
         id: :string,
@@ -569,6 +658,36 @@ RSpec.describe Lancelot::Dataset do
       end
     end
 
+    describe "column projection" do
+      it "reads only selected columns" do
+        expect(dataset.all(select: [:text]).first).to eq({ text: "Ruby is great" })
+        expect(dataset.first(2, select: ["score"]).map(&:keys)).to eq([[:score], [:score]])
+        expect(dataset.first(select: :text)).to eq({ text: "Ruby is great" })
+      end
+
+      it "skips excluded columns" do
+        expect(dataset.all(exclude: [:score]).map(&:keys).uniq).to eq([[:text]])
+        expect(dataset.each(exclude: [:text]).first.keys).to eq([:score])
+      end
+
+      it "projects filtered scans" do
+        results = dataset.where("score > 0.8", select: [:text])
+        expect(results).to contain_exactly({ text: "Ruby is great" }, { text: "JavaScript is everywhere" })
+      end
+
+      it "raises an error when both select and exclude are given" do
+        expect {
+          dataset.all(select: [:text], exclude: [:score])
+        }.to raise_error(ArgumentError, /Cannot specify both select and exclude/)
+      end
+
+      it "raises an error for unknown excluded columns" do
+        expect {
+          dataset.all(exclude: [:missing])
+        }.to raise_error(ArgumentError, /Column missing not found/)
+      end
+    end
+
     describe "Enumerable methods" do
       it "supports map" do
         texts = dataset.map { |doc| doc[:text] }
@@ -770,6 +889,386 @@ RSpec.describe Lancelot::Dataset do
     end
   end
 
+  describe "row ids" do
+    let(:dataset) do
+      schema = { text: :string, vector: { type: "vector", dimension: 3 } }
+      Lancelot::Dataset.create(dataset_path, schema: schema)
+    end
+
+    before do
+      dataset.add_documents([{ text: "apple pie", vector: [1.0, 0.0, 0.0] }])
+      dataset.add_documents([{ text: "banana bread", vector: [0.0, 1.0, 0.0] }])
+      dataset.add_documents([{ text: "apple pie", vector: [0.0, 0.0, 1.0] }])
+    end
+
+    it "returns _rowid only when asked" do
+      expect(dataset.all.first).not_to have_key(:_rowid)
+
+      row_ids = dataset.all(with_row_id: true).map { |doc| doc[:_rowid] }
+      expect(row_ids).to all(be_an(Integer))
+      expect(row_ids.uniq.size).to eq(3)
+    end
+
+    it "returns _rowid from searches and filters" do
+      dataset.create_text_index("text")
+
+      expect(dataset.vector_search([1.0, 0.0, 0.0], column: "vector", limit: 1, with_row_id: true).first).to have_key(:_rowid)
+      expect(dataset.text_search("apple", column: "text", with_row_id: true).map { |doc| doc[:_rowid] }.uniq.size).to eq(2)
+      expect(dataset.where("text = 'banana bread'", with_row_id: true).first).to have_key(:_rowid)
+      expect(dataset.first(with_row_id: true)).to have_key(:_rowid)
+
+      results = dataset.hybrid_search("apple", vector: [0.0, 0.0, 1.0], text_column: "text", with_row_id: true)
+      expect(results.map { |doc| doc[:_rowid] }).to all(be_an(Integer))
+    end
+
+    it "takes rows by row id in the order given" do
+      row_ids = dataset.all(with_row_id: true).map { |doc| doc[:_rowid] }
+
+      rows = dataset.take(row_ids.reverse, columns: [:text])
+      expect(rows).to eq([{ text: "apple pie" }, { text: "banana bread" }, { text: "apple pie" }])
+      expect(dataset.take(row_ids[1]).first[:vector]).to eq([0.0, 1.0, 0.0])
+    end
+
+    it "takes rows by offset" do
+      rows = dataset.take_offsets([2, 0], columns: :vector)
+      expect(rows).to eq([{ vector: [0.0, 0.0, 1.0] }, { vector: [1.0, 0.0, 0.0] }])
+    end
+
+    it "keeps row ids stable across compaction by default" do
+      before = dataset.all(with_row_id: true).to_h { |doc| [doc[:_rowid], doc[:vector]] }
+
+      dataset.compact(target_rows_per_fragment: 1000)
+
+      expect(dataset.take(before.keys, columns: [:vector]).map { |doc| doc[:vector] }).to eq(before.values)
+    end
+
+    context "without move-stable row ids" do
+      let(:dataset) do
+        schema = { text: :string, vector: { type: "vector", dimension: 3 } }
+        Lancelot::Dataset.create(dataset_path, schema: schema, enable_move_stable_row_ids: false)
+      end
+
+      it "changes row ids on compaction" do
+        before = dataset.all(with_row_id: true).map { |doc| doc[:_rowid] }
+
+        dataset.compact(target_rows_per_fragment: 1000)
+
+        expect(dataset.all(with_row_id: true).map { |doc| doc[:_rowid] }).not_to eq(before)
+      end
+    end
+  end
+
+  describe "parameterised filters" do
+    let(:dataset) do
+      schema = {
+        author: :string,
+        year: :int32,
+        published_on: :date,
+        vector: { type: "vector", dimension: 2 }
+      }
+      Lancelot::Dataset.create(dataset_path, schema: schema)
+    end
+
+    before do
+      dataset.add_documents([
+        { author: "O'Brien", year: 2019, published_on: Date.new(2019, 3, 1), vector: [1.0, 0.0] },
+        { author: "O'Brien", year: 2022, published_on: Date.new(2022, 5, 1), vector: [0.9, 0.1] },
+        { author: "Smith", year: 2023, published_on: Date.new(2023, 1, 1), vector: [0.0, 1.0] }
+      ])
+    end
+
+    it "binds positional parameters in where" do
+      results = dataset.where("author = ? AND year > ?", "O'Brien", 2020)
+      expect(results.map { |doc| doc[:year] }).to eq([2022])
+    end
+
+    it "binds named parameters in where" do
+      results = dataset.where("author = :author OR year = :year", { author: "Smith", year: 2019 })
+      expect(results.map { |doc| doc[:year] }).to contain_exactly(2019, 2023)
+    end
+
+    it "binds named parameters that share a name with an option" do
+      results = dataset.where("year > :limit", { limit: 2020 }, limit: 1)
+      expect(results.size).to eq(1)
+    end
+
+    it "rejects misspelt options rather than taking them as parameters" do
+      expect { dataset.where("year > :year", { year: 2020 }, limt: 1) }.to raise_error(ArgumentError, /unknown keyword: :limt/)
+    end
+
+    it "rejects named parameters no placeholder uses" do
+      expect { dataset.where("year > :year", { year: 2020, yaer: 2021 }) }.to raise_error(ArgumentError, /Unused parameters: yaer/)
+      expect { dataset.where("year > 2020", 1) }.to raise_error(ArgumentError, /Expected 0 parameters but got 1/)
+    end
+
+    it "parses filters with parameters the same way as without" do
+      with_row_ids = dataset.where("_rowid >= 1", with_row_id: true)
+      expect(dataset.where("_rowid >= ?", 1, with_row_id: true)).to eq(with_row_ids)
+
+      quoted = dataset.where("`year` > 2020 AND author = 'Smith'")
+      expect(dataset.where("`year` > ? AND author = ?", 2020, "Smith")).to eq(quoted)
+      expect(quoted.map { |doc| doc[:year] }).to eq([2023])
+    end
+
+    it "leaves placeholders inside backtick-quoted identifiers alone" do
+      expect { dataset.where("`year?` > ?", 2020) }.to raise_error(RuntimeError, /year\?/)
+    end
+
+    it "binds dates" do
+      results = dataset.where("published_on >= ?", Date.new(2022, 1, 1))
+      expect(results.map { |doc| doc[:year] }).to contain_exactly(2022, 2023)
+    end
+
+    it "treats parameters as values, not SQL" do
+      expect(dataset.where("author = ?", "x' OR '1' = '1")).to be_empty
+    end
+
+    it "binds parameters in search filters" do
+      results = dataset.vector_search([1.0, 0.0], column: "vector", limit: 3, filter: ["year > ?", 2020])
+      expect(results.map { |doc| doc[:year] }).to eq([2022, 2023])
+
+      results = dataset.vector_search([1.0, 0.0], column: "vector", limit: 3,
+                                      filter: ["author = :author", { author: "O'Brien" }])
+      expect(results.map { |doc| doc[:year] }).to eq([2019, 2022])
+    end
+
+    it "binds parameters in filter_scan" do
+      expect(dataset.filter_scan("year < ?", nil, params: [2020]).map { |doc| doc[:year] }).to eq([2019])
+    end
+
+    it "checks the parameters match the placeholders" do
+      expect { dataset.where("year > ?", 1, 2) }.to raise_error(ArgumentError, /Expected 1 parameters but got 2/)
+      expect { dataset.where("year > ? AND author = :author", 1) }.to raise_error(ArgumentError, /Named placeholders need named parameters/)
+      expect { dataset.where("year > ?", [2020]) }.to raise_error(ArgumentError, /Unsupported parameter type: Array/)
+    end
+  end
+
+  describe "#sql" do
+    let(:dataset) do
+      schema = { title: :string, category: :string, score: :float64, author_id: :int32 }
+      Lancelot::Dataset.create(dataset_path, schema: schema)
+    end
+
+    before do
+      dataset.add_documents([
+        { title: "Rails", category: "ruby", score: 4.0, author_id: 1 },
+        { title: "Sinatra", category: "ruby", score: 3.0, author_id: 2 },
+        { title: "Django", category: "python", score: 5.0, author_id: 1 },
+        { title: "Flask's guide", category: "python", score: 2.0, author_id: 2 }
+      ])
+    end
+
+    it "groups and aggregates the dataset as the table self" do
+      results = dataset.sql("SELECT category, count(*) AS n, avg(score) AS mean FROM self GROUP BY category ORDER BY category")
+
+      expect(results).to eq([
+        { category: "python", n: 2, mean: 3.5 },
+        { category: "ruby", n: 2, mean: 3.5 }
+      ])
+    end
+
+    it "selects distinct values" do
+      results = dataset.sql("SELECT DISTINCT category FROM self ORDER BY category")
+      expect(results.map { |row| row[:category] }).to eq(["python", "ruby"])
+    end
+
+    it "binds positional parameters" do
+      results = dataset.sql("SELECT title FROM self WHERE category = ? AND score > ? ORDER BY title", "ruby", 3.5)
+      expect(results).to eq([{ title: "Rails" }])
+    end
+
+    it "binds named parameters" do
+      results = dataset.sql("SELECT title FROM self WHERE title = :title OR author_id = :author",
+                            { title: "Flask's guide", author: 1 })
+      expect(results.map { |row| row[:title] }).to contain_exactly("Rails", "Django", "Flask's guide")
+    end
+
+    it "leaves placeholders inside string literals alone" do
+      results = dataset.sql("SELECT title FROM self WHERE title <> 'what?' AND category = ?", "python")
+      expect(results.size).to eq(2)
+    end
+
+    it "joins other datasets" do
+      authors = Lancelot::Dataset.create(File.join(temp_dir, "authors"), schema: { id: :int32, name: :string })
+      authors.add_documents([{ id: 1, name: "Ada" }, { id: 2, name: "Grace" }])
+
+      results = dataset.sql(<<~SQL, "Ada", tables: { authors: authors })
+        SELECT self.title FROM self JOIN authors ON self.author_id = authors.id
+        WHERE authors.name = ? ORDER BY self.title
+      SQL
+
+      expect(results.map { |row| row[:title] }).to eq(["Django", "Rails"])
+    end
+
+    it "joins the dataset with itself" do
+      results = dataset.sql(<<~SQL, tables: { other: dataset })
+        SELECT COUNT(*) AS pairs FROM self JOIN other ON self.author_id = other.author_id
+      SQL
+
+      expect(results.first[:pairs]).to be > 0
+    end
+
+    it "raises an error when a joined dataset is used from an add_column block" do
+      other = Lancelot::Dataset.create(File.join(temp_dir, "other"), schema: { id: :int32 })
+      other.add_documents([{ id: 1 }])
+
+      expect {
+        other.add_column(:copy, type: :int32, checkpoint: false) do |rows|
+          dataset.sql("SELECT COUNT(*) AS n FROM self JOIN other ON self.author_id = other.id", tables: { other: other })
+          rows.map { 1 }
+        end
+      }.to raise_error(RuntimeError, /in use by an add_column block/)
+    end
+
+    it "rejects statements that change data" do
+      expect { dataset.sql("DELETE FROM self") }.to raise_error(RuntimeError, /DML not supported/)
+      expect(dataset.count).to eq(4)
+    end
+
+    it "checks the parameter count" do
+      expect { dataset.sql("SELECT * FROM self WHERE score > ?", 1, 2) }.to raise_error(ArgumentError, /Expected 1 parameters but got 2/)
+      expect { dataset.sql("SELECT * FROM self WHERE score > :min", { max: 1 }) }.to raise_error(ArgumentError, /Missing parameter: min/)
+    end
+
+    it "rejects named parameters no placeholder uses" do
+      expect {
+        dataset.sql("SELECT * FROM self WHERE score > :min", { min: 1, mni: 2 })
+      }.to raise_error(ArgumentError, /Unused parameters: mni/)
+    end
+
+    it "binds parameters named like options" do
+      results = dataset.sql("SELECT title FROM self WHERE author_id = :tables AND score > :as_of", { tables: 1, as_of: 4.5 })
+      expect(results).to eq([{ title: "Django" }])
+    end
+
+    it "does not take named parameters as keywords" do
+      expect { dataset.sql("SELECT * FROM self WHERE score > :min", min: 1) }.to raise_error(ArgumentError, /unknown keyword: :min/)
+    end
+  end
+
+  describe "ordering" do
+    let(:dataset) do
+      schema = { title: :string, category: :string, published_at: :date, id: :int32 }
+      Lancelot::Dataset.create(dataset_path, schema: schema)
+    end
+
+    before do
+      dataset.add_documents([
+        { id: 1, title: "Old ruby", category: "ruby", published_at: Date.new(2023, 1, 1) },
+        { id: 2, title: "New ruby", category: "ruby", published_at: Date.new(2024, 6, 1) },
+        { id: 3, title: "Python", category: "python", published_at: Date.new(2024, 7, 1) },
+        { id: 4, title: "Same day ruby", category: "ruby", published_at: Date.new(2024, 6, 1) },
+        { id: 5, title: "Undated ruby", category: "ruby", published_at: nil }
+      ])
+    end
+
+    it "orders filtered rows by several columns" do
+      results = dataset.where("category = 'ruby'", order_by: [[:published_at, :desc], [:id, :asc]])
+      expect(results.map { |doc| doc[:id] }).to eq([5, 2, 4, 1])
+    end
+
+    it "keeps the top rows with a limit" do
+      results = dataset.where("category = 'ruby' AND published_at IS NOT NULL", limit: 2,
+                              order_by: { published_at: :desc, id: :desc })
+      expect(results.map { |doc| doc[:id] }).to eq([4, 2])
+    end
+
+    it "sorts with a top-k sort when there is a limit" do
+      expect(dataset.scan_limit(2, order_by: [:id, :desc], explain: true)).to match(/SortExec: TopK\(fetch=2\)/)
+      expect(dataset.where("id > 0", limit: 3, offset: 1, order_by: :id, explain: true)).to match(/SortExec: TopK\(fetch=4\)/)
+    end
+
+    it "orders scans" do
+      expect(dataset.scan_all(order_by: :title).map { |doc| doc[:title] }.first).to eq("New ruby")
+      expect(dataset.scan_limit(1, order_by: [:id, :desc]).first[:id]).to eq(5)
+      expect(dataset.scan_limit(2, offset: 1, order_by: [:id, :desc]).map { |doc| doc[:id] }).to eq([4, 3])
+    end
+
+    it "rejects unknown columns and directions" do
+      expect { dataset.where("id > 0", order_by: [:missing, :asc]) }.to raise_error(ArgumentError, /Column missing not found/)
+      expect { dataset.where("id > 0", order_by: { id: :sideways }) }.to raise_error(ArgumentError, /Unknown sort direction/)
+    end
+  end
+
+  describe "pagination" do
+    let(:dataset) do
+      schema = { text: :string, position: :int32, vector: { type: "vector", dimension: 2 } }
+      Lancelot::Dataset.create(dataset_path, schema: schema)
+    end
+
+    before do
+      dataset.add_documents(10.times.map do |i|
+        { text: "page item #{i}", position: i, vector: [i.to_f, 0.0] }
+      end)
+    end
+
+    it "skips rows with offset" do
+      expect(dataset.scan_limit(3, offset: 4).map { |doc| doc[:position] }).to eq([4, 5, 6])
+      expect(dataset.where("position >= 2", limit: 2, offset: 3).map { |doc| doc[:position] }).to eq([5, 6])
+      expect(dataset.vector_search([0.0, 0.0], limit: 2, offset: 2).map { |doc| doc[:position] }).to eq([2, 3])
+    end
+
+    it "skips text search results with offset" do
+      dataset.create_text_index("text")
+
+      all = dataset.text_search("page", limit: 10).map { |doc| doc[:position] }
+      page = dataset.text_search("page", limit: 3, offset: 3).map { |doc| doc[:position] }
+      expect(page).to eq(all[3, 3])
+    end
+
+    it "pages through results with cursors" do
+      positions = []
+      page = dataset.where("position >= 0", limit: 4, cursor: true)
+      loop do
+        expect(page).to be_a(Lancelot::Page)
+        expect(page.first).not_to have_key(:_rowid)
+        positions.concat(page.map { |doc| doc[:position] })
+        break unless page.next_cursor
+
+        page = dataset.where("position >= 0", limit: 4, cursor: page.next_cursor)
+      end
+
+      expect(positions).to eq((0...10).to_a)
+    end
+
+    it "keeps reading the cursor's version while new rows are added" do
+      first_page = dataset.vector_search([0.0, 0.0], limit: 3, cursor: true)
+      dataset.add_documents([{ text: "newcomer", position: 99, vector: [0.5, 0.0] }])
+
+      second_page = dataset.vector_search([0.0, 0.0], limit: 3, cursor: first_page.next_cursor)
+      expect(first_page.map { |doc| doc[:position] }).to eq([0, 1, 2])
+      expect(second_page.map { |doc| doc[:position] }).to eq([3, 4, 5])
+    end
+
+    it "ends with no next cursor" do
+      page = dataset.scan_limit(20, cursor: true)
+      expect(page.size).to eq(10)
+      expect(page.next_cursor).to be_nil
+    end
+
+    it "fetches the skipped rows too, so deep pages cost O(offset)" do
+      plan = dataset.vector_search([0.0, 0.0], limit: 2, offset: 5, explain: true)
+      expect(plan).to match(/fetch=7\b/)
+    end
+
+    it "raises instead of skipping or repeating rows when results shift under a cursor" do
+      page = dataset.vector_search([0.0, 0.0], limit: 3, cursor: true, with_row_id: true)
+      # A cursor whose last row is not the row before the next page, as when tied scores reorder
+      shifted = Lancelot::Cursor.encode(version: dataset.version, offset: 3, last_row_id: page.first[:_rowid],
+                                        last_score: page.first[:_distance])
+
+      expect {
+        dataset.vector_search([0.0, 0.0], limit: 3, cursor: shifted)
+      }.to raise_error(ArgumentError, /Cursor does not match version/)
+    end
+
+    it "rejects invalid cursors" do
+      expect { dataset.scan_limit(2, cursor: "nonsense") }.to raise_error(ArgumentError, /Invalid cursor/)
+      expect { dataset.scan_limit(2, cursor: true, offset: 2) }.to raise_error(ArgumentError, /Cannot specify cursor/)
+      expect { dataset.where("position > 0", cursor: true) }.to raise_error(ArgumentError, /limit is required/)
+    end
+  end
+
   describe "vector search" do
     let(:dataset) do
       schema = { 
@@ -792,6 +1291,18 @@ RSpec.describe Lancelot::Dataset do
       it "creates a vector index" do
         expect { dataset.create_vector_index("vector") }.not_to raise_error
       end
+
+      it "raises an error for unknown index types" do
+        expect {
+          dataset.create_vector_index("vector", type: :lsh)
+        }.to raise_error(ArgumentError, /Unknown vector index type: lsh/)
+      end
+
+      it "raises an error for non-vector columns" do
+        expect {
+          dataset.create_vector_index("text")
+        }.to raise_error(ArgumentError, /not a vector column/)
+      end
     end
 
     describe "#vector_search" do
@@ -822,6 +1333,34 @@ RSpec.describe Lancelot::Dataset do
           dataset.vector_search("not an array", column: "vector")
         }.to raise_error(ArgumentError, /must be an array/)
       end
+
+      it "filters results" do
+        results = dataset.vector_search([0.1, 0.2, 0.3], column: "vector", limit: 2, filter: "score < 0.82")
+
+        expect(results.map { |doc| doc[:text] }).to eq(["JavaScript development"])
+      end
+
+      it "prefilters by default so the limit is still filled" do
+        results = dataset.vector_search([0.1, 0.2, 0.3], column: "vector", limit: 1, filter: "score < 0.88")
+        expect(results.map { |doc| doc[:text] }).to eq(["Python coding"])
+      end
+
+      it "returns only selected columns and the distance" do
+        results = dataset.vector_search([0.1, 0.2, 0.3], column: "vector", limit: 1, select: [:text])
+        expect(results.first.keys).to contain_exactly(:text, :_distance)
+      end
+
+      it "excludes the vector column" do
+        results = dataset.vector_search([0.1, 0.2, 0.3], column: "vector", limit: 1, exclude: [:vector])
+        expect(results.first).not_to have_key(:vector)
+        expect(results.first[:text]).to eq("Ruby programming")
+      end
+
+      it "filters only the nearest rows with prefilter: false" do
+        results = dataset.vector_search([0.1, 0.2, 0.3], column: "vector", limit: 1,
+                                        filter: "score < 0.88", prefilter: false)
+        expect(results).to be_empty
+      end
     end
 
     describe "#nearest_neighbors" do
@@ -839,115 +1378,1223 @@ RSpec.describe Lancelot::Dataset do
     end
   end
 
-  describe "text search" do
+  describe "vector index types" do
     let(:dataset) do
-      schema = { 
-        title: :string,
-        content: :string,
-        category: :string,
-        year: :int64
-      }
-      Lancelot::Dataset.create(dataset_path, schema: schema)
+      Lancelot::Dataset.create(dataset_path, schema: { text: :string, vector: { type: "vector", dimension: 16 } })
     end
 
     before do
-      dataset.add_documents([
-        { title: "Ruby on Rails", content: "Web framework for Ruby", category: "web", year: 2023 },
-        { title: "Django Python", content: "Web framework for Python", category: "web", year: 2024 },
-        { title: "Ruby Gems", content: "Package manager for Ruby", category: "tools", year: 2023 },
-        { title: "Python Packages", content: "PyPI is the Python package index", category: "tools", year: 2024 }
-      ])
+      rng = Random.new(42)
+      dataset.add_documents((0...512).map { |i| { text: "doc #{i}", vector: Array.new(16) { rng.rand } } })
     end
 
-    describe "#create_text_index" do
-      it "creates a text index on a column" do
-        expect { dataset.create_text_index("title") }.not_to raise_error
-        expect { dataset.create_text_index("content") }.not_to raise_error
+    %i[ivf_pq ivf_sq ivf_hnsw_sq ivf_hnsw_pq].each do |type|
+      it "creates an #{type} index" do
+        dataset.create_vector_index("vector", type: type, num_partitions: 4)
+
+        query = dataset.first[:vector]
+        results = dataset.vector_search(query, column: "vector", limit: 5)
+        expect(results.length).to eq(5)
       end
     end
 
-    describe "#text_search" do
-      context "with text indices" do
-        before do
-          dataset.create_text_index("title")
+    it "accepts PQ and HNSW parameters" do
+      expect {
+        dataset.create_vector_index("vector", type: "IVF_HNSW_PQ", num_partitions: 2, num_sub_vectors: 4,
+                                    num_bits: 8, m: 8, ef_construction: 50, sample_rate: 64, max_iterations: 10)
+      }.not_to raise_error
+    end
+  end
+
+  describe "vector search tuning" do
+    let(:dataset) do
+      Lancelot::Dataset.create(dataset_path, schema: { text: :string, vector: { type: "vector", dimension: 16 } })
+    end
+
+    let(:rng) { Random.new(7) }
+
+    before do
+      dataset.add_documents((0...512).map { |i| { text: "doc #{i}", vector: Array.new(16) { rng.rand } } })
+    end
+
+    it "accepts nprobes and refine_factor for IVF indexes" do
+      dataset.create_vector_index("vector", type: :ivf_pq, num_partitions: 8)
+
+      query = dataset.first[:vector]
+      results = dataset.vector_search(query, column: "vector", limit: 5, nprobes: 8, refine_factor: 4)
+      expect(results.length).to eq(5)
+      expect(results.first[:text]).to eq("doc 0")
+    end
+
+    it "probes the requested number of IVF partitions" do
+      dataset.create_vector_index("vector", type: :ivf_pq, num_partitions: 8)
+      query = dataset.first[:vector]
+
+      one = dataset.vector_search(query, column: "vector", limit: 5, nprobes: 1, explain: true)
+      all = dataset.vector_search(query, column: "vector", limit: 5, nprobes: 8, explain: true)
+
+      expect(one).to match(/maximum_nprobes=(Some\()?1\b/)
+      expect(all).to match(/maximum_nprobes=(Some\()?8\b/)
+    end
+
+    it "accepts an adaptive nprobes range" do
+      dataset.create_vector_index("vector", type: :ivf_pq, num_partitions: 8)
+      query = dataset.first[:vector]
+
+      plan = dataset.vector_search(query, column: "vector", limit: 5, minimum_nprobes: 2, maximum_nprobes: 6,
+                                   explain: true)
+      expect(plan).to match(/minimum_nprobes=2\b/)
+      expect(plan).to match(/maximum_nprobes=(Some\()?6\b/)
+      expect(dataset.vector_search(query, column: "vector", limit: 5, minimum_nprobes: 2).length).to eq(5)
+    end
+
+    it "accepts ef for HNSW indexes" do
+      dataset.create_vector_index("vector", type: :ivf_hnsw_sq, num_partitions: 2)
+
+      results = dataset.vector_search(dataset.first[:vector], column: "vector", limit: 3, ef: 64)
+      expect(results.length).to eq(3)
+    end
+
+    it "runs an exact search with use_index: false" do
+      dataset.create_vector_index("vector", type: :ivf_pq, num_partitions: 8)
+
+      query = dataset.first[:vector]
+      results = dataset.vector_search(query, column: "vector", limit: 1, use_index: false)
+      expect(results.first[:text]).to eq("doc 0")
+    end
+
+    it "skips unindexed rows with fast_search: true" do
+      dataset.create_vector_index("vector", num_partitions: 2)
+      dataset.add_documents([{ text: "unindexed", vector: Array.new(16, 5.0) }])
+
+      query = Array.new(16, 5.0)
+      expect(dataset.vector_search(query, column: "vector", limit: 1).first[:text]).to eq("unindexed")
+      expect(dataset.vector_search(query, column: "vector", limit: 1, fast_search: true).first[:text]).not_to eq("unindexed")
+    end
+
+    it "passes tuning options through nearest_neighbors" do
+      results = dataset.nearest_neighbors(dataset.first[:vector], k: 2, column: "vector", use_index: false)
+      expect(results.length).to eq(2)
+    end
+  end
+
+  describe "distance metrics" do
+    let(:dataset) do
+      Lancelot::Dataset.create(dataset_path, schema: { text: :string, vector: { type: "vector", dimension: 2 } })
+    end
+
+    before do
+      dataset.add_documents([
+        { text: "same direction, far", vector: [10.0, 0.0] },
+        { text: "other direction, near", vector: [0.5, 0.6] }
+      ])
+    end
+
+    it "uses L2 distance by default" do
+      results = dataset.vector_search([1.0, 0.0], column: "vector", limit: 1)
+      expect(results.first[:text]).to eq("other direction, near")
+    end
+
+    it "supports cosine distance for brute-force search" do
+      results = dataset.vector_search([1.0, 0.0], column: "vector", limit: 1, metric: :cosine)
+      expect(results.first[:text]).to eq("same direction, far")
+    end
+
+    it "supports dot product distance" do
+      results = dataset.vector_search([1.0, 0.0], column: "vector", limit: 1, metric: :dot)
+      expect(results.first[:text]).to eq("same direction, far")
+    end
+
+    it "creates indexes with a metric" do
+      dataset.create_vector_index("vector", metric: :cosine)
+
+      results = dataset.vector_search([1.0, 0.0], column: "vector", limit: 1, metric: :cosine)
+      expect(results.first[:text]).to eq("same direction, far")
+    end
+
+    it "raises an error for unknown metrics" do
+      expect {
+        dataset.vector_search([1.0, 0.0], column: "vector", metric: :manhattan)
+      }.to raise_error(ArgumentError, /Unknown distance metric: manhattan/)
+    end
+
+    context "with a metric recorded in the schema" do
+      let(:dataset) do
+        schema = { text: :string, vector: { type: "vector", dimension: 2, metric: :cosine } }
+        Lancelot::Dataset.create(dataset_path, schema: schema)
+      end
+
+      it "reports the metric in the schema" do
+        expect(dataset.schema[:vector]).to eq({ type: "vector", dimension: 2, metric: "cosine" })
+      end
+
+      it "uses the column metric by default" do
+        results = dataset.vector_search([1.0, 0.0], column: "vector", limit: 1)
+        expect(results.first[:text]).to eq("same direction, far")
+      end
+
+      it "lets the search override the column metric" do
+        results = dataset.vector_search([1.0, 0.0], column: "vector", limit: 1, metric: :l2)
+        expect(results.first[:text]).to eq("other direction, near")
+      end
+    end
+  end
+
+  describe "binary vectors" do
+    let(:dataset) do
+      schema = { text: :string, vector: { type: "vector", dimension: 2, element_type: :uint8, metric: :hamming } }
+      Lancelot::Dataset.create(dataset_path, schema: schema)
+    end
+
+    it "supports hamming distance" do
+      dataset.add_documents([
+        { text: "one bit off", vector: [0b1111_0000, 0b0000_0001] },
+        { text: "all bits off", vector: [0b0000_1111, 0b1111_1110] }
+      ])
+
+      results = dataset.vector_search([0b1111_0000, 0b0000_0000], column: "vector", limit: 1)
+      expect(results.first[:text]).to eq("one bit off")
+      expect(results.first[:vector]).to eq([0b1111_0000, 0b0000_0001])
+    end
+  end
+
+  describe "scalar indexes" do
+    let(:dataset) do
+      schema = {
+        title: :string,
+        tenant_id: :int64,
+        status: :string,
+        published_on: :date,
+        tags: { type: "list" }
+      }
+      Lancelot::Dataset.create(dataset_path, schema: schema)
+    end
+
+    before do
+      dataset.add_documents((0...100).map do |i|
+        {
+          title: "doc #{i}",
+          tenant_id: i % 10,
+          status: %w[open closed pending][i % 3],
+          published_on: Date.new(2024, 1, 1) + i,
+          tags: i.even? ? ["even", "number"] : ["odd", "number"]
+        }
+      end)
+    end
+
+    it "round-trips date and list values" do
+      doc = dataset.first
+      expect(doc[:published_on]).to eq(Date.new(2024, 1, 1))
+      expect(doc[:tags]).to eq(["even", "number"])
+    end
+
+    # Filters answered by a scalar index show up as an index query in the plan
+    let(:index_scan) { /ScalarIndexQuery|MaterializeIndex/ }
+
+    it "scans without an index query before an index exists" do
+      expect(dataset.where("tenant_id = 4", explain: true)).not_to match(index_scan)
+    end
+
+    it "filters on a btree index" do
+      dataset.create_scalar_index(:tenant_id)
+
+      expect(dataset.where("tenant_id = 4", explain: true)).to match(index_scan)
+      results = dataset.where("tenant_id = 4")
+      expect(results.length).to eq(10)
+      expect(results.map { |doc| doc[:tenant_id] }.uniq).to eq([4])
+    end
+
+    it "filters dates on a btree index" do
+      dataset.create_scalar_index(:published_on, type: :btree)
+
+      expect(dataset.where("published_on < date '2024-01-04'", explain: true)).to match(index_scan)
+      results = dataset.where("published_on < date '2024-01-04'")
+      expect(results.map { |doc| doc[:title] }).to contain_exactly("doc 0", "doc 1", "doc 2")
+    end
+
+    it "filters on a bitmap index" do
+      dataset.create_scalar_index(:status, type: :bitmap)
+
+      expect(dataset.where("status IN ('open', 'pending')", explain: true)).to match(index_scan)
+      results = dataset.where("status IN ('open', 'pending')")
+      expect(results.length).to eq(67)
+    end
+
+    it "filters on a label_list index" do
+      dataset.create_scalar_index(:tags, type: :label_list)
+
+      expect(dataset.where("array_has_any(tags, ['odd'])", explain: true)).to match(index_scan)
+      results = dataset.where("array_has_any(tags, ['odd'])")
+      expect(results.length).to eq(50)
+      expect(results.map { |doc| doc[:tags] }.uniq).to eq([["odd", "number"]])
+    end
+
+    it "raises an error for unknown index types" do
+      expect {
+        dataset.create_scalar_index(:status, type: :hash)
+      }.to raise_error(ArgumentError, /Unknown scalar index type: hash/)
+    end
+
+    it "raises an error when the index type does not suit the column" do
+      expect {
+        dataset.create_scalar_index(:status, type: :label_list)
+      }.to raise_error(ArgumentError, /Cannot create a label_list index on column status/)
+    end
+  end
+
+  describe "index management" do
+    let(:dataset) do
+      schema = { title: :string, status: :string, vector: { type: "vector", dimension: 4 } }
+      Lancelot::Dataset.create(dataset_path, schema: schema)
+    end
+
+    before do
+      dataset.add_documents((0...64).map do |i|
+        { title: "doc #{i}", status: i.even? ? "open" : "closed", vector: [i, i + 1, i + 2, i + 3].map(&:to_f) }
+      end)
+    end
+
+    it "returns no indexes for a new dataset" do
+      expect(dataset.indexes).to eq([])
+    end
+
+    it "lists indexes with their type, columns and row coverage" do
+      dataset.create_vector_index("vector", num_partitions: 2)
+      dataset.create_scalar_index(:status, type: :bitmap, name: "status_bitmap")
+      dataset.create_text_index(:title)
+
+      indexes = dataset.indexes.to_h { |index| [index[:name], index] }
+      expect(indexes.keys).to contain_exactly("vector_idx", "status_bitmap", "title_idx")
+
+      vector_index = indexes["vector_idx"]
+      expect(vector_index[:type]).to eq("ivf_flat")
+      expect(vector_index[:columns]).to eq(["vector"])
+      expect(vector_index[:params]).to include(metric_type: "l2", num_partitions: 2)
+      expect(vector_index[:num_indexed_rows]).to eq(64)
+      expect(vector_index[:num_unindexed_rows]).to eq(0)
+      expect(vector_index[:size_bytes]).to be > 0
+
+      expect(indexes["status_bitmap"][:type]).to eq("bitmap")
+      expect(indexes["status_bitmap"][:params]).to eq({})
+      expect(indexes["title_idx"][:type]).to eq("inverted")
+    end
+
+    it "counts rows added since the index was built as unindexed" do
+      dataset.create_scalar_index(:status)
+      dataset.add_documents([{ title: "late", status: "open", vector: [1.0, 1.0, 1.0, 1.0] }])
+
+      index = dataset.indexes.first
+      expect(index[:num_indexed_rows]).to eq(64)
+      expect(index[:num_unindexed_rows]).to eq(1)
+    end
+
+    it "returns Lance's statistics for an index" do
+      dataset.create_scalar_index(:status, name: "status_idx")
+
+      stats = dataset.index_stats("status_idx")
+      expect(stats[:name]).to eq("status_idx")
+      expect(stats[:num_indexed_rows]).to eq(64)
+    end
+
+    it "drops an index" do
+      dataset.create_scalar_index(:status)
+      dataset.drop_index("status_idx")
+
+      expect(dataset.indexes).to eq([])
+      expect(dataset.where("status = 'open'").length).to eq(32)
+    end
+
+    it "renames an index" do
+      dataset.create_scalar_index(:status)
+      dataset.rename_index("status_idx", "status_lookup")
+
+      expect(dataset.indexes.map { |index| index[:name] }).to eq(["status_lookup"])
+      expect(dataset.index_stats("status_lookup")[:num_indexed_rows]).to eq(64)
+      expect(dataset.where("status = 'open'", explain: true)).to match(/ScalarIndexQuery|MaterializeIndex/)
+    end
+
+    it "raises an error when renaming to an existing name or a missing index" do
+      dataset.create_scalar_index(:status)
+      dataset.create_scalar_index(:title, name: "title_btree")
+
+      expect { dataset.rename_index("status_idx", "title_btree") }.to raise_error(ArgumentError, /already exists/)
+      expect { dataset.rename_index("missing_idx", "other") }.to raise_error(ArgumentError, /not found/)
+    end
+
+    it "refuses to replace an index when replace: false" do
+      dataset.create_scalar_index(:status)
+
+      expect {
+        dataset.create_scalar_index(:status, replace: false)
+      }.to raise_error(RuntimeError)
+    end
+  end
+
+  describe "#optimize_indexes" do
+    let(:dataset) do
+      Lancelot::Dataset.create(dataset_path, schema: { title: :string, vector: { type: "vector", dimension: 4 } })
+    end
+
+    let(:rng) { Random.new(3) }
+
+    def documents(count, offset = 0)
+      (0...count).map { |i| { title: "doc #{offset + i}", vector: Array.new(4) { rng.rand } } }
+    end
+
+    before do
+      dataset.add_documents(documents(64))
+      dataset.create_vector_index("vector", num_partitions: 2)
+      dataset.create_text_index(:title)
+    end
+
+    def unindexed_rows(name)
+      dataset.index_stats(name)[:num_unindexed_rows]
+    end
+
+    it "indexes rows appended since the index was built" do
+      dataset.add_documents(documents(16, 64))
+      expect(unindexed_rows("vector_idx")).to eq(16)
+
+      dataset.optimize_indexes
+      expect(unindexed_rows("vector_idx")).to eq(0)
+      expect(unindexed_rows("title_idx")).to eq(0)
+    end
+
+    it "optimizes only indexes on the given columns" do
+      dataset.add_documents(documents(16, 64))
+
+      dataset.optimize_indexes(columns: [:title])
+      expect(unindexed_rows("title_idx")).to eq(0)
+      expect(unindexed_rows("vector_idx")).to eq(16)
+    end
+
+    it "accepts merge and retrain options" do
+      dataset.add_documents(documents(16, 64))
+
+      expect {
+        dataset.optimize_indexes(columns: "vector", num_indices_to_merge: 2, retrain: true)
+      }.not_to raise_error
+      expect(unindexed_rows("vector_idx")).to eq(0)
+    end
+
+    it "raises an error when no index covers the columns" do
+      expect {
+        dataset.optimize_indexes(columns: [:missing])
+      }.to raise_error(ArgumentError, /No indexes on columns: missing/)
+    end
+
+    context "with auto_optimize_indexes" do
+      before { dataset.auto_optimize_indexes = 10 }
+
+      it "leaves small appends unindexed" do
+        dataset.add_documents(documents(5, 64))
+        expect(unindexed_rows("vector_idx")).to eq(5)
+      end
+
+      it "optimizes once more rows than the threshold are unindexed" do
+        dataset.add_documents(documents(5, 64))
+        dataset.add_documents(documents(6, 69))
+        expect(unindexed_rows("vector_idx")).to eq(0)
+        expect(unindexed_rows("title_idx")).to eq(0)
+      end
+
+      it "optimizes after add_data" do
+        dataset.add_data(documents(11, 64), {})
+        expect(unindexed_rows("vector_idx")).to eq(0)
+      end
+
+      it "keeps the setting when the dataset is reopened" do
+        reopened = Lancelot::Dataset.open(dataset_path)
+        expect(reopened.auto_optimize_indexes).to eq(10)
+
+        reopened.add_documents(documents(11, 64))
+        expect(reopened.indexes.find { |index| index[:name] == "vector_idx" }[:num_unindexed_rows]).to eq(0)
+      end
+
+      it "can be turned off" do
+        dataset.auto_optimize_indexes = nil
+        dataset.add_documents(documents(11, 64))
+
+        expect(dataset.auto_optimize_indexes).to be_nil
+        expect(unindexed_rows("vector_idx")).to eq(11)
+      end
+    end
+  end
+
+  describe "text search" do
+    let(:dataset) do
+      schema = { 
+        title: :string,
+        content: :string,
+        category: :string,
+        year: :int64
+      }
+      Lancelot::Dataset.create(dataset_path, schema: schema)
+    end
+
+    before do
+      dataset.add_documents([
+        { title: "Ruby on Rails", content: "Web framework for Ruby", category: "web", year: 2023 },
+        { title: "Django Python", content: "Web framework for Python", category: "web", year: 2024 },
+        { title: "Ruby Gems", content: "Package manager for Ruby", category: "tools", year: 2023 },
+        { title: "Python Packages", content: "PyPI is the Python package index", category: "tools", year: 2024 }
+      ])
+    end
+
+    describe "#create_text_index" do
+      it "creates a text index on a column" do
+        expect { dataset.create_text_index("title") }.not_to raise_error
+        expect { dataset.create_text_index("content") }.not_to raise_error
+      end
+
+      it "stems words for the configured language" do
+        dataset.create_text_index("content", language: :english, stem: true)
+
+        results = dataset.text_search("frameworks", column: "content")
+        expect(results.map { |doc| doc[:title] }).to contain_exactly("Ruby on Rails", "Django Python")
+      end
+
+      it "reports the chosen settings" do
+        dataset.create_text_index("content", base_tokenizer: :whitespace, language: :german, stem: true,
+                                  remove_stop_words: true, ascii_folding: true, lower_case: true,
+                                  max_token_length: 40, with_position: true)
+
+        params = dataset.indexes.find { |index| index[:name] == "content_idx" }[:params]
+        expect(params).to include(base_tokenizer: "whitespace", language: "German", stem: true,
+                                  remove_stop_words: true, ascii_folding: true, lower_case: true,
+                                  max_token_length: 40, with_position: true)
+      end
+
+      it "raises an error for unknown tokenizers" do
+        expect {
+          dataset.create_text_index("content", base_tokenizer: :icu)
+        }.to raise_error(ArgumentError, /Unknown tokenizer: icu/)
+      end
+
+      it "raises an error for unsupported languages" do
+        expect {
+          dataset.create_text_index("content", language: :klingon)
+        }.to raise_error(ArgumentError, /Unsupported language: Klingon/)
+      end
+    end
+
+    describe "#text_search" do
+      context "with text indices" do
+        before do
+          dataset.create_text_index("title")
           dataset.create_text_index("content")
           dataset.create_text_index("category")
         end
 
-        it "searches a single column" do
-          results = dataset.text_search("ruby", column: "title")
-          expect(results).to be_an(Array)
-          expect(results.length).to eq(2)
-          titles = results.map { |doc| doc[:title] }
-          expect(titles).to include("Ruby on Rails", "Ruby Gems")
-        end
+        it "searches a single column" do
+          results = dataset.text_search("ruby", column: "title")
+          expect(results).to be_an(Array)
+          expect(results.length).to eq(2)
+          titles = results.map { |doc| doc[:title] }
+          expect(titles).to include("Ruby on Rails", "Ruby Gems")
+        end
+
+        it "searches with default column" do
+          # Default is "text" column which doesn't exist
+          # This will raise an error because the column doesn't exist
+          expect {
+            dataset.text_search("ruby")
+          }.to raise_error(RuntimeError, /Column text not found/)
+        end
+
+        it "searches multiple columns" do
+          results = dataset.text_search("framework", columns: ["title", "content"])
+          expect(results.length).to be >= 2
+        end
+
+        it "is case insensitive" do
+          results = dataset.text_search("RUBY", column: "title")
+          expect(results.length).to eq(2)
+        end
+
+        it "handles multi-word queries" do
+          results = dataset.text_search("package manager", column: "content")
+          expect(results.length).to be >= 1
+          expect(results.first[:title]).to eq("Ruby Gems")
+        end
+
+        it "filters results" do
+          results = dataset.text_search("ruby", column: "title", filter: "year = 2023 AND category = 'tools'")
+          expect(results.map { |doc| doc[:title] }).to eq(["Ruby Gems"])
+        end
+
+        it "returns only selected columns" do
+          results = dataset.text_search("ruby", column: "title", select: [:title])
+          expect(results.map { |doc| doc.keys - [:_score] }.uniq).to eq([[:title]])
+        end
+
+        it "filters multi-column results" do
+          results = dataset.text_search("framework", columns: ["title", "content"], filter: "year = 2024")
+          expect(results.map { |doc| doc[:title] }).to eq(["Django Python"])
+        end
+
+        it "raises error for non-string query" do
+          expect {
+            dataset.text_search(123, column: "title")
+          }.to raise_error(ArgumentError, /must be a string/)
+        end
+
+        context "with structured queries" do
+          let(:query) { Lancelot::Query }
+
+          before do
+            dataset.create_text_index("content", with_position: true)
+          end
+
+          it "matches phrases" do
+            results = dataset.text_search(query.phrase("framework for ruby", column: "content"))
+            expect(results.map { |doc| doc[:title] }).to eq(["Ruby on Rails"])
+          end
+
+          it "matches phrases with slop" do
+            expect(dataset.text_search(query.phrase("web ruby", column: "content"))).to be_empty
+
+            results = dataset.text_search(query.phrase("web ruby", column: "content", slop: 2))
+            expect(results.map { |doc| doc[:title] }).to eq(["Ruby on Rails"])
+          end
+
+          it "combines boolean clauses" do
+            results = dataset.text_search(query.boolean(
+              must: [query.match("framework", column: "content")],
+              must_not: [query.match("python", column: "content")]
+            ))
+            expect(results.map { |doc| doc[:title] }).to eq(["Ruby on Rails"])
+          end
+
+          it "supports minimum_should_match" do
+            results = dataset.text_search(query.boolean(
+              should: %w[web framework ruby].map { |word| query.match(word, column: "content") },
+              minimum_should_match: 3
+            ))
+            expect(results.map { |doc| doc[:title] }).to eq(["Ruby on Rails"])
+
+            results = dataset.text_search(query.boolean(
+              should: %w[web package ruby].map { |word| query.match(word, column: "content") },
+              minimum_should_match: 2
+            ))
+            expect(results.map { |doc| doc[:title] }).to contain_exactly("Ruby on Rails", "Ruby Gems")
+          end
+
+          it "matches misspellings with fuzzy queries" do
+            expect(dataset.text_search(query.match("framwork", column: "content"))).to be_empty
+
+            results = dataset.text_search(query.fuzzy("framwork", distance: 1, column: "content"))
+            expect(results.length).to eq(2)
+          end
+
+          it "boosts columns" do
+            results = dataset.text_search("ruby", columns: ["title^5", "content"])
+            expect(results.map { |doc| doc[:title] }).to contain_exactly("Ruby on Rails", "Ruby Gems")
+          end
+
+          it "raises an error when a column is also given" do
+            expect {
+              dataset.text_search(query.match("ruby"), column: "title")
+            }.to raise_error(ArgumentError, /Cannot specify column or columns with a structured query/)
+          end
+
+          it "raises an error for impossible minimum_should_match" do
+            expect {
+              dataset.text_search(query.boolean(should: [query.match("ruby", column: "content")], minimum_should_match: 2))
+            }.to raise_error(ArgumentError, /only 1 should clauses/)
+          end
+
+          it "raises an error quickly when minimum_should_match has too many combinations" do
+            should = (1..30).map { |i| query.match("word#{i}", column: "content") }
+            started = Process.clock_gettime(Process::CLOCK_MONOTONIC)
+            expect {
+              dataset.text_search(query.boolean(should: should, minimum_should_match: 15))
+            }.to raise_error(ArgumentError, /too many combinations/)
+            expect(Process.clock_gettime(Process::CLOCK_MONOTONIC) - started).to be < 1
+          end
+        end
+
+        it "raises error when both column and columns specified" do
+          expect {
+            dataset.text_search("ruby", column: "title", columns: ["content"])
+          }.to raise_error(ArgumentError, /Cannot specify both/)
+        end
+      end
+    end
+
+    describe "#where" do
+      it "filters with simple conditions" do
+        results = dataset.where("year = 2023")
+        expect(results.length).to eq(2)
+        expect(results.map { |doc| doc[:title] }).to include("Ruby on Rails", "Ruby Gems")
+      end
+
+      it "filters with compound conditions" do
+        results = dataset.where("category = 'web' AND year = 2024")
+        expect(results.length).to eq(1)
+        expect(results.first[:title]).to eq("Django Python")
+      end
+
+      it "filters with LIKE patterns" do
+        results = dataset.where("title LIKE '%Python%'")
+        expect(results.length).to eq(2)
+      end
+
+      it "supports limit parameter" do
+        results = dataset.where("category = 'web'", limit: 1)
+        expect(results.length).to eq(1)
+      end
+
+      it "handles OR conditions" do
+        results = dataset.where("title LIKE '%Rails%' OR title LIKE '%Django%'")
+        expect(results.length).to eq(2)
+      end
+    end
+  end
+
+  describe "schema evolution" do
+    let(:dataset) do
+      schema = {
+        title: :string,
+        year: :int32,
+        score: :float32,
+        vector: { type: "vector", dimension: 3 }
+      }
+      Lancelot::Dataset.create(dataset_path, schema: schema)
+    end
+
+    before do
+      dataset.add_documents([
+        { title: "Ruby", year: 2023, score: 0.5, vector: [0.1, 0.2, 0.3] },
+        { title: "Python", year: 2024, score: 0.25, vector: [0.4, 0.5, 0.6] }
+      ])
+    end
+
+    describe "#add_columns" do
+      it "adds columns computed from SQL expressions" do
+        dataset.add_columns(doubled: "score * 2")
+
+        expect(dataset.schema).to have_key(:doubled)
+        docs = dataset.to_a.sort_by { |doc| doc[:title] }
+        expect(docs.map { |doc| doc[:doubled] }).to eq([0.5, 1.0])
+      end
+
+      it "adds all-null columns from a schema" do
+        dataset.add_columns(schema: { language: :string })
+
+        expect(dataset.schema[:language]).to eq("string")
+        expect(dataset.to_a.map { |doc| doc[:language] }).to all(be_nil)
+
+        dataset.add_documents([{ title: "Go", year: 2025, score: 0.1, vector: [0.7, 0.8, 0.9], language: "en" }])
+        expect(dataset.where("language = 'en'").map { |doc| doc[:title] }).to eq(["Go"])
+      end
+
+      it "persists the new schema after reopening" do
+        dataset.add_columns(schema: { language: :string })
+
+        reopened = Lancelot::Dataset.open(dataset_path)
+        expect(reopened.schema[:language]).to eq("string")
+      end
+
+      it "requires expressions or a schema" do
+        expect { dataset.add_columns }.to raise_error(ArgumentError, /must be provided/)
+      end
+    end
+
+    describe "#drop_columns" do
+      it "removes columns from the dataset" do
+        dataset.drop_columns(:score)
+
+        expect(dataset.schema).not_to have_key(:score)
+        expect(dataset.first).not_to have_key(:score)
+        expect(dataset.count).to eq(2)
+      end
+    end
+
+    describe "#alter_columns" do
+      it "renames columns" do
+        dataset.rename_column(:title, :headline)
+
+        expect(dataset.schema).to have_key(:headline)
+        expect(dataset.schema).not_to have_key(:title)
+        expect(dataset.to_a.map { |doc| doc[:headline] }).to contain_exactly("Ruby", "Python")
+      end
+
+      it "casts int32 columns to int64" do
+        dataset.alter_columns(year: { cast: :int64 })
+
+        expect(dataset.schema[:year]).to eq("int64")
+        expect(dataset.to_a.map { |doc| doc[:year] }).to contain_exactly(2023, 2024)
+      end
+
+      it "casts float32 vectors to float16" do
+        dataset.alter_columns(vector: { cast: { type: "vector", dimension: 3, element_type: :float16 } })
+
+        expect(dataset.schema[:vector][:element_type]).to eq("float16")
+        first = dataset.to_a.find { |doc| doc[:title] == "Ruby" }
+        expect(first[:vector][0]).to be_within(0.001).of(0.1)
+      end
+    end
+  end
+
+  describe "#add_column" do
+    let(:dataset) do
+      schema = { title: :string, score: :float32 }
+      Lancelot::Dataset.create(dataset_path, schema: schema)
+    end
+
+    before do
+      dataset.add_documents((1..10).map { |i| { title: "doc #{i}", score: i.to_f } })
+    end
+
+    it "backfills a column from a block" do
+      dataset.add_column(:vector_v2, type: { type: "vector", dimension: 2 }, batch_size: 3) do |rows|
+        rows.map { |row| [row[:score], row[:score] * 2] }
+      end
+
+      expect(dataset.schema[:vector_v2]).to eq({ type: "vector", dimension: 2 })
+      doc = dataset.to_a.find { |d| d[:title] == "doc 4" }
+      expect(doc[:vector_v2]).to eq([4.0, 8.0])
+    end
+
+    it "passes only the requested columns to the block" do
+      seen_keys = []
+      dataset.add_column(:title_length, type: :int64, columns: [:title]) do |rows|
+        seen_keys |= rows.flat_map(&:keys)
+        rows.map { |row| row[:title].length }
+      end
+
+      expect(seen_keys).to eq([:title])
+      expect(dataset.to_a.map { |d| d[:title_length] }).to all(be >= 5)
+    end
+
+    it "calls the block in batches" do
+      batch_sizes = []
+      dataset.add_column(:doubled, type: :float32, batch_size: 4) do |rows|
+        batch_sizes << rows.size
+        rows.map { |row| row[:score] * 2 }
+      end
+
+      expect(batch_sizes.sum).to eq(10)
+      expect(batch_sizes.max).to be <= 4
+    end
+
+    it "removes the checkpoint after a successful backfill" do
+      dataset.add_column(:doubled, type: :float32) { |rows| rows.map { |row| row[:score] * 2 } }
 
-        it "searches with default column" do
-          # Default is "text" column which doesn't exist
-          # This will raise an error because the column doesn't exist
-          expect {
-            dataset.text_search("ruby")
-          }.to raise_error(RuntimeError, /Column text not found/)
-        end
+      expect(File.exist?(File.join(dataset_path, "_lancelot_checkpoints", "doubled"))).to be false
+    end
 
-        it "searches multiple columns" do
-          results = dataset.text_search("framework", columns: ["title", "content"])
-          expect(results.length).to be >= 2
+    it "resumes from the checkpoint after an interruption" do
+      calls = 0
+      expect {
+        dataset.add_column(:doubled, type: :float32, batch_size: 2) do |rows|
+          calls += 1
+          raise "interrupted" if calls == 3
+          rows.map { |row| row[:score] * 2 }
         end
+      }.to raise_error(RuntimeError, /interrupted/)
+      expect(dataset.schema).not_to have_key(:doubled)
 
-        it "is case insensitive" do
-          results = dataset.text_search("RUBY", column: "title")
-          expect(results.length).to eq(2)
-        end
+      resumed_rows = 0
+      dataset.add_column(:doubled, type: :float32, batch_size: 2) do |rows|
+        resumed_rows += rows.size
+        rows.map { |row| row[:score] * 2 }
+      end
 
-        it "handles multi-word queries" do
-          results = dataset.text_search("package manager", column: "content")
-          expect(results.length).to be >= 1
-          expect(results.first[:title]).to eq("Ruby Gems")
+      expect(resumed_rows).to be < 10
+      expect(dataset.to_a.map { |d| d[:doubled] }).to match_array((1..10).map { |i| i * 2.0 })
+    end
+
+    it "discards a checkpoint left by a backfill with different settings" do
+      expect {
+        dataset.add_column(:doubled, type: :float32, batch_size: 2) do |rows|
+          raise "interrupted" if rows.first[:score] > 4
+          rows.map { -1.0 }
         end
+      }.to raise_error(RuntimeError, /interrupted/)
 
-        it "raises error for non-string query" do
-          expect {
-            dataset.text_search(123, column: "title")
-          }.to raise_error(ArgumentError, /must be a string/)
+      resumed_rows = 0
+      dataset.add_column(:doubled, type: :float32, batch_size: 5) do |rows|
+        resumed_rows += rows.size
+        rows.map { |row| row[:score] * 2 }
+      end
+
+      expect(resumed_rows).to eq(10)
+      expect(dataset.to_a.map { |d| d[:doubled] }).to match_array((1..10).map { |i| i * 2.0 })
+    end
+
+    it "only deletes its own files from a checkpoint directory given by the caller" do
+      checkpoint_dir = File.join(temp_dir, "shared")
+      FileUtils.mkdir_p(checkpoint_dir)
+      File.write(File.join(checkpoint_dir, "notes.txt"), "keep me")
+
+      expect {
+        dataset.add_column(:doubled, type: :float32, batch_size: 2, checkpoint: checkpoint_dir) do |rows|
+          raise "interrupted" if rows.first[:score] > 4
+          rows.map { |row| row[:score] * 2 }
         end
+      }.to raise_error(RuntimeError, /interrupted/)
+      expect(File.exist?(File.join(checkpoint_dir, "manifest.json"))).to be true
 
-        it "raises error when both column and columns specified" do
-          expect {
-            dataset.text_search("ruby", column: "title", columns: ["content"])
-          }.to raise_error(ArgumentError, /Cannot specify both/)
+      # Different settings discard the checkpoint, then success removes it
+      dataset.add_column(:doubled, type: :float32, batch_size: 5, checkpoint: checkpoint_dir) do |rows|
+        rows.map { |row| row[:score] * 2 }
+      end
+
+      expect(Dir.children(checkpoint_dir)).to eq(["notes.txt"])
+      expect(File.read(File.join(checkpoint_dir, "notes.txt"))).to eq("keep me")
+    end
+
+    it "raises an error when the block uses the dataset" do
+      expect {
+        dataset.add_column(:doubled, type: :float32, checkpoint: false) do |rows|
+          dataset.count
+          rows.map { |row| row[:score] * 2 }
         end
+      }.to raise_error(RuntimeError, /in use by an add_column block/)
+
+      expect(dataset.count).to eq(10)
+    end
+
+    it "raises an error when the block returns the wrong number of values" do
+      expect {
+        dataset.add_column(:broken, type: :float32, checkpoint: false) { |_rows| [1.0] }
+      }.to raise_error(RuntimeError, /Block returned 1 values/)
+    end
+
+    it "requires a block" do
+      expect { dataset.add_column(:doubled, type: :float32) }.to raise_error(ArgumentError, /requires a block/)
+    end
+  end
+
+  describe "versioning" do
+    let(:dataset) do
+      Lancelot::Dataset.create(dataset_path, schema: { text: :string, score: :float32 })
+    end
+
+    before do
+      dataset.add_documents([{ text: "first", score: 0.1 }])
+      dataset.add_documents([{ text: "second", score: 0.2 }])
+    end
+
+    describe "#versions" do
+      it "lists every committed version" do
+        versions = dataset.versions
+
+        expect(versions.map { |v| v[:version] }).to eq([1, 2, 3])
+        expect(versions.map { |v| v[:num_rows] }).to eq([0, 1, 2])
+        expect(versions.last[:operation]).to eq("Append")
+        expect(versions.last[:timestamp]).to be_a(Time)
+        expect(versions.last[:timestamp]).to be <= Time.now
       end
     end
 
-    describe "#where" do
-      it "filters with simple conditions" do
-        results = dataset.where("year = 2023")
-        expect(results.length).to eq(2)
-        expect(results.map { |doc| doc[:title] }).to include("Ruby on Rails", "Ruby Gems")
+    describe "#version" do
+      it "returns the current version number" do
+        expect(dataset.version).to eq(3)
       end
+    end
 
-      it "filters with compound conditions" do
-        results = dataset.where("category = 'web' AND year = 2024")
-        expect(results.length).to eq(1)
-        expect(results.first[:title]).to eq("Django Python")
+    describe ".open with version:" do
+      it "opens an older version read-only" do
+        old = Lancelot::Dataset.open(dataset_path, version: 2)
+
+        expect(old.version).to eq(2)
+        expect(old.count).to eq(1)
+        expect(old).to be_read_only
+        expect { old.add_documents([{ text: "third", score: 0.3 }]) }.to raise_error(RuntimeError, /read-only/)
       end
+    end
 
-      it "filters with LIKE patterns" do
-        results = dataset.where("title LIKE '%Python%'")
-        expect(results.length).to eq(2)
+    describe "#checkout" do
+      it "returns a read-only handle at the given version" do
+        old = dataset.checkout(2)
+
+        expect(old.to_a.map { |doc| doc[:text] }).to eq(["first"])
+        expect(old).to be_read_only
+        expect(dataset).not_to be_read_only
+        expect(dataset.count).to eq(2)
       end
+    end
 
-      it "supports limit parameter" do
-        results = dataset.where("category = 'web'", limit: 1)
-        expect(results.length).to eq(1)
+    describe "tags" do
+      it "tags the current version by default" do
+        dataset.tag("latest-import")
+
+        expect(dataset.tags).to eq({ "latest-import" => 3 })
       end
 
-      it "handles OR conditions" do
-        results = dataset.where("title LIKE '%Rails%' OR title LIKE '%Django%'")
-        expect(results.length).to eq(2)
+      it "tags a specific version" do
+        dataset.tag(:"eval-2026-09", 2)
+
+        expect(dataset.tags["eval-2026-09"]).to eq(2)
+      end
+
+      it "opens a dataset by tag" do
+        dataset.tag("eval-2026-09", 2)
+        dataset.add_documents([{ text: "third", score: 0.3 }])
+
+        snapshot = Lancelot::Dataset.open(dataset_path, tag: "eval-2026-09")
+        expect(snapshot.version).to eq(2)
+        expect(snapshot.count).to eq(1)
+        expect(snapshot).to be_read_only
+      end
+
+      it "deletes tags" do
+        dataset.tag("temporary", 2)
+        dataset.delete_tag("temporary")
+
+        expect(dataset.tags).to be_empty
+      end
+
+      it "rejects both version and tag" do
+        dataset.tag("snapshot", 2)
+
+        expect {
+          Lancelot::Dataset.open(dataset_path, version: 2, tag: "snapshot")
+        }.to raise_error(ArgumentError, /both version and tag/)
+      end
+
+      it "raises an error for unknown tags" do
+        expect {
+          Lancelot::Dataset.open(dataset_path, tag: "missing")
+        }.to raise_error(RuntimeError)
+      end
+    end
+
+    describe "time travel" do
+      let(:before_import) { Time.now }
+
+      before do
+        before_import
+        sleep 0.01
+        dataset.add_documents([{ text: "third", score: 0.3 }])
+      end
+
+      it "opens the version that was current at a given time" do
+        snapshot = Lancelot::Dataset.open(dataset_path, as_of: before_import)
+
+        expect(snapshot.version).to eq(3)
+        expect(snapshot.count).to eq(2)
+        expect(snapshot).to be_read_only
+      end
+
+      it "filters as of a given time" do
+        expect(dataset.where("score > 0.25").length).to eq(1)
+        expect(dataset.where("score > 0.25", as_of: before_import)).to be_empty
+        expect(dataset.where("score > 0.15", as_of: before_import).length).to eq(1)
+      end
+
+      it "raises an error for times before the dataset existed" do
+        expect {
+          Lancelot::Dataset.open(dataset_path, as_of: Time.at(0))
+        }.to raise_error(ArgumentError, /No version/)
+      end
+
+      it "rejects combining as_of with a version" do
+        expect {
+          Lancelot::Dataset.open(dataset_path, version: 2, as_of: Time.now)
+        }.to raise_error(ArgumentError, /Only one of/)
+      end
+    end
+
+    describe "#restore" do
+      it "commits an older version as the new latest version" do
+        dataset.restore(2)
+
+        expect(dataset.version).to eq(4)
+        expect(dataset.to_a.map { |doc| doc[:text] }).to eq(["first"])
+        expect(Lancelot::Dataset.open(dataset_path).count).to eq(1)
+      end
+
+      it "keeps the restored-over versions in the history" do
+        dataset.restore(2)
+
+        expect(dataset.checkout(3).count).to eq(2)
+      end
+    end
+  end
+
+  describe "#diff" do
+    let(:dataset) do
+      Lancelot::Dataset.create(dataset_path, schema: { id: :int64, text: :string, score: :float32 })
+    end
+
+    before do
+      dataset.add_documents([{ id: 1, text: "first", score: 0.1 }])
+      dataset.add_documents([{ id: 2, text: "second", score: 0.2 }])
+    end
+
+    it "returns rows inserted between two versions" do
+      changes = dataset.diff(2, 3)
+
+      expect(changes[:inserted]).to eq([{ id: 2, text: "second", score: 0.2.to_f.round(7).then { |_| dataset.where("id = 2").first[:score] } }])
+      expect(changes[:deleted]).to be_empty
+      expect(changes[:updated]).to be_empty
+    end
+
+    it "returns rows deleted between two versions" do
+      dataset.restore(2)
+      changes = dataset.diff(3, 4)
+
+      expect(changes[:deleted].map { |doc| doc[:id] }).to eq([2])
+      expect(changes[:inserted]).to be_empty
+    end
+
+    it "returns rows updated between two versions" do
+      dataset.add_columns(doubled: "score * 2")
+      changes = dataset.diff(3, 4)
+
+      expect(changes[:updated].map { |doc| doc[:id] }).to contain_exactly(1, 2)
+      expect(changes[:updated].first).to have_key(:doubled)
+      expect(changes[:inserted]).to be_empty
+      expect(changes[:deleted]).to be_empty
+    end
+
+    it "reports only inserts and deletes without a key" do
+      dataset.add_columns(doubled: "score * 2")
+      changes = dataset.diff(3, 4, key: nil)
+
+      expect(changes[:inserted].length).to eq(2)
+      expect(changes[:deleted].length).to eq(2)
+      expect(changes[:updated]).to be_empty
+    end
+
+    it "yields changes when given a block" do
+      yielded = []
+      dataset.diff(1, 3) { |type, doc| yielded << [type, doc[:id]] }
+
+      expect(yielded).to contain_exactly([:inserted, 1], [:inserted, 2])
+    end
+
+    it "raises an error when the key column does not exist" do
+      expect { dataset.diff(1, 3, key: :uuid) }.to raise_error(ArgumentError, /Key column uuid not found/)
+    end
+  end
+
+  describe "#cleanup" do
+    let(:dataset) do
+      Lancelot::Dataset.create(dataset_path, schema: { text: :string, score: :float32 })
+    end
+
+    before do
+      dataset.add_documents([{ text: "first", score: 0.1 }])
+      dataset.add_documents([{ text: "second", score: 0.2 }])
+      dataset.restore(1)
+      sleep 0.01
+    end
+
+    it "removes old versions and the files only they reference" do
+      stats = dataset.cleanup(older_than: 0)
+
+      expect(stats[:versions_removed]).to eq(3)
+      expect(stats[:bytes_removed]).to be > 0
+      expect(stats[:files]).not_to be_empty
+      expect(stats[:files].map { |f| f[:path] }).to all(satisfy { |p| !File.exist?(File.join(dataset_path, p)) })
+      expect(dataset.versions.map { |v| v[:version] }).to eq([4])
+      expect(dataset.count).to eq(0)
+    end
+
+    it "accepts a Time cutoff" do
+      stats = dataset.cleanup(older_than: Time.now - 3600)
+
+      expect(stats[:versions_removed]).to eq(0)
+      expect(dataset.versions.length).to eq(4)
+    end
+
+    it "lists what would be removed in dry-run mode without deleting anything" do
+      plan = dataset.cleanup(older_than: 0, dry_run: true)
+
+      expect(plan[:versions_removed]).to eq(3)
+      expect(plan[:files]).not_to be_empty
+      expect(plan[:files].map { |f| f[:path] }).to all(satisfy { |p| File.exist?(File.join(dataset_path, p)) })
+      expect(plan[:bytes_removed]).to eq(plan[:files].sum { |f| f[:size] })
+      expect(dataset.versions.length).to eq(4)
+    end
+
+    it "lists exactly the files a real cleanup removes" do
+      files_on_disk = -> { Dir.glob("**/*", base: dataset_path).select { |f| File.file?(File.join(dataset_path, f)) } }
+
+      plan = dataset.cleanup(older_than: 0, dry_run: true)
+      before = files_on_disk.call
+      stats = dataset.cleanup(older_than: 0)
+      removed = before - files_on_disk.call
+
+      expect(plan[:files].map { |f| f[:path] }).to match_array(removed)
+      expect(plan.keys).to match_array(stats.keys)
+      expect(plan).to eq(stats)
+    end
+
+    it "lists unreferenced files only with delete_unverified: true" do
+      orphan = File.join(dataset_path, "data", "orphan.lance")
+      File.write(orphan, "left behind by a failed write")
+
+      kept = dataset.cleanup(older_than: 0, dry_run: true)
+      unverified = dataset.cleanup(older_than: 0, delete_unverified: true, dry_run: true)
+
+      expect(kept[:files].map { |f| f[:path] }).not_to include("data/orphan.lance")
+      expect(unverified[:files].map { |f| f[:path] }).to include("data/orphan.lance")
+
+      dataset.cleanup(older_than: 0, delete_unverified: true)
+      expect(File.exist?(orphan)).to be false
+    end
+
+    it "keeps tagged versions" do
+      dataset.tag("keep-me", 3)
+      dataset.cleanup(older_than: 0)
+
+      expect(dataset.versions.map { |v| v[:version] }).to eq([3, 4])
+      expect(Lancelot::Dataset.open(dataset_path, tag: "keep-me").count).to eq(2)
+    end
+
+    it "raises instead of keeping old tagged versions with error_if_tagged: true" do
+      dataset.tag("old", 3)
+
+      expect { dataset.cleanup(older_than: 0, error_if_tagged: true) }.to raise_error(RuntimeError, /tag/)
+      expect { dataset.cleanup(older_than: 0, error_if_tagged: true, dry_run: true) }.to raise_error(RuntimeError, /tag/)
+      expect(dataset.tags).to eq({ "old" => 3 })
+      expect(dataset.versions.length).to eq(4)
+    end
+  end
+
+  describe "#compact" do
+    let(:dataset) do
+      schema = { text: :string, vector: { type: "vector", dimension: 3 } }
+      Lancelot::Dataset.create(dataset_path, schema: schema)
+    end
+
+    before do
+      10.times do |i|
+        dataset.add_documents([{ text: "doc #{i}", vector: [i * 0.1, 0.5, 1.0 - i * 0.1] }])
       end
     end
+
+    it "merges small fragments and returns metrics" do
+      metrics = dataset.compact(target_rows_per_fragment: 1000)
+
+      expect(metrics[:fragments_removed]).to eq(10)
+      expect(metrics[:fragments_added]).to eq(1)
+      expect(metrics[:files_removed]).to eq(10)
+      expect(metrics[:files_added]).to eq(1)
+      expect(dataset.count).to eq(10)
+    end
+
+    it "keeps vector indexes valid" do
+      dataset.create_vector_index("vector")
+      dataset.compact
+
+      results = dataset.vector_search([0.0, 0.5, 1.0], column: "vector", limit: 1)
+      expect(results.first[:text]).to eq("doc 0")
+    end
+
+    it "does nothing when fragments are already large enough" do
+      dataset.compact
+      metrics = dataset.compact
+
+      expect(metrics[:fragments_removed]).to eq(0)
+      expect(metrics[:fragments_added]).to eq(0)
+    end
   end
 
   describe "Ruby object methods" do
@@ -1125,6 +2772,19 @@ RSpec.describe Lancelot::Dataset do
       expect(scores).to eq(scores.sort.reverse)
     end
 
+    it "applies the filter to both searches" do
+      results = dataset.hybrid_search(
+        "framework",
+        vector: [0.15, 0.25, 0.35],
+        vector_column: "embedding",
+        text_column: "content",
+        limit: 4,
+        filter: "title LIKE 'Python%'"
+      )
+
+      expect(results.map { |doc| doc[:title] }).to eq(["Python Django"])
+    end
+
     it "works with only vector search" do
       query_vector = [0.1, 0.2, 0.3]
       results = dataset.hybrid_search(
@@ -1249,5 +2909,118 @@ RSpec.describe Lancelot::Dataset do
         expect(count).to eq(1)
       end
     end
+
+    it "keeps rows with identical content apart" do
+      dataset.add_documents([
+        { title: "Ruby Gems", content: "Package manager for Ruby", embedding: [0.2, 0.3, 0.4] }
+      ])
+
+      results = dataset.hybrid_search(
+        "Ruby",
+        vector: [0.2, 0.3, 0.4],
+        vector_column: "embedding",
+        text_column: "title",
+        limit: 5
+      )
+
+      expect(results.count { |doc| doc[:title] == "Ruby Gems" }).to eq(2)
+    end
+
+    it "returns the component scores of each row" do
+      results = dataset.hybrid_search(
+        "Ruby",
+        vector: [0.9, 0.9, 0.9],
+        vector_column: "embedding",
+        text_column: "title",
+        limit: 4
+      )
+
+      both = results.find { |doc| doc[:title] == "Ruby Gems" }
+      expect(both[:_distance]).to be_a(Float)
+      expect(both[:_score]).to be > 0
+      expect(both[:_relevance_score]).to eq(both[:rrf_score])
+
+      vector_only = results.find { |doc| doc[:title] == "JavaScript Express" }
+      expect(vector_only[:_score]).to be_nil
+    end
+
+    it "weights the searches in RRF" do
+      search = lambda do |weights|
+        dataset.hybrid_search(
+          "Ruby",
+          vector: [0.7, 0.8, 0.9],
+          vector_column: "embedding",
+          text_column: "title",
+          limit: 1,
+          weights: weights
+        ).first[:title]
+      end
+
+      expect(search.call(vector: 1.0, text: 0.0)).to eq("JavaScript Express")
+      expect(search.call(vector: 0.0, text: 1.0)).to start_with("Ruby")
+    end
+
+    it "combines normalised scores with linear fusion" do
+      %i[minmax zscore].each do |normalization|
+        results = dataset.hybrid_search(
+          "Ruby",
+          vector: [0.1, 0.2, 0.3],
+          vector_column: "embedding",
+          text_column: "title",
+          limit: 4,
+          fusion: :linear,
+          normalization: normalization
+        )
+
+        expect(results.first(2).map { |doc| doc[:title] }).to contain_exactly("Ruby on Rails", "Ruby Gems")
+        expect(results.first).not_to have_key(:rrf_score)
+        scores = results.map { |doc| doc[:_relevance_score] }
+        expect(scores).to eq(scores.sort.reverse)
+      end
+    end
+
+    it "orders candidates by vector distance with distance fusion" do
+      results = dataset.hybrid_search(
+        "Ruby",
+        vector: [0.7, 0.8, 0.9],
+        vector_column: "embedding",
+        text_column: "title",
+        limit: 4,
+        fusion: :distance,
+        candidates: 1
+      )
+
+      # Rows only the text search found still get a distance
+      expect(results.map { |doc| doc[:_distance] }).to all(be_a(Float))
+      distances = results.map { |doc| doc[:_distance] }
+      expect(distances).to eq(distances.sort)
+      expect(results.first[:title]).to eq("JavaScript Express")
+    end
+
+    it "orders by dot distance with distance fusion when dot products exceed 2" do
+      dot_dataset = Lancelot::Dataset.create(File.join(temp_dir, "dot_dataset"), schema: {
+        title: :string,
+        embedding: { type: "vector", dimension: 2, metric: :dot }
+      })
+      dot_dataset.add_documents([
+        { title: "Ruby opposite", embedding: [-5.0, 0.0] },
+        { title: "Ruby close", embedding: [3.0, 0.0] },
+        { title: "Ruby closest", embedding: [10.0, 0.0] }
+      ])
+      dot_dataset.create_text_index("title")
+
+      results = dot_dataset.hybrid_search("Ruby", vector: [1.0, 0.0], vector_column: "embedding",
+                                          text_column: "title", limit: 3, fusion: :distance)
+
+      expect(results.map { |doc| doc[:title] }).to eq(["Ruby closest", "Ruby close", "Ruby opposite"])
+      expect(results.map { |doc| doc[:_relevance_score] }).to eq([9.0, 2.0, -6.0])
+    end
+
+    it "rejects unknown fusion methods" do
+      expect {
+        dataset.hybrid_search("Ruby", vector: [0.1, 0.2, 0.3], vector_column: "embedding",
+                              text_column: "title", fusion: :magic)
+      }.to raise_error(ArgumentError, /Unknown fusion: magic/)
+    end
   end
 end
\ No newline at end of file
@@ -0,0 +1,76 @@
+# frozen_string_literal: true
+
+require "lancelot/query"
+
+RSpec.describe Lancelot::Query do
+  describe ".match" do
+    it "builds a match query, dropping unset options" do
+      expect(described_class.match("ruby gems", column: :title)).to eq(
+        { type: "match", terms: "ruby gems", column: "title" }
+      )
+    end
+
+    it "includes boost, fuzziness and operator" do
+      query = described_class.match("ruby", boost: 2.0, fuzziness: 1, operator: :and)
+      expect(query).to include(boost: 2.0, fuzziness: 1, operator: "and")
+    end
+  end
+
+  describe ".fuzzy" do
+    it "builds a match query with automatic edit distance by default" do
+      expect(described_class.fuzzy("rubby", column: "title")).to include(type: "match", fuzziness: :auto)
+      expect(described_class.fuzzy("rubby", distance: 2)).to include(fuzziness: 2)
+    end
+  end
+
+  describe ".phrase" do
+    it "builds a phrase query with slop" do
+      expect(described_class.phrase("web framework", column: "content", slop: 1)).to eq(
+        { type: "phrase", terms: "web framework", column: "content", slop: 1 }
+      )
+    end
+  end
+
+  describe ".multi_match" do
+    it "parses per-column boosts" do
+      query = described_class.multi_match("ruby", columns: ["title^3", "content"])
+      expect(query).to eq({ type: "multi_match", terms: "ruby", columns: ["title", "content"], boosts: [3.0, 1.0] })
+    end
+
+    it "omits boosts when no column is boosted" do
+      expect(described_class.multi_match("ruby", columns: [:title, :content])).not_to have_key(:boosts)
+    end
+
+    it "raises an error for malformed boosts" do
+      expect { described_class.multi_match("ruby", columns: ["title^high"]) }.to raise_error(ArgumentError)
+    end
+
+    it "requires at least one column" do
+      expect { described_class.multi_match("ruby", columns: []) }.to raise_error(ArgumentError, /at least one column/)
+    end
+  end
+
+  describe ".boolean" do
+    it "combines clauses" do
+      ruby = described_class.match("ruby")
+      python = described_class.match("python")
+
+      query = described_class.boolean(must: [ruby], must_not: [python], minimum_should_match: 1)
+      expect(query).to eq({ type: "boolean", must: [ruby], should: [], must_not: [python], minimum_should_match: 1 })
+    end
+
+    it "requires a must or should clause" do
+      expect {
+        described_class.boolean(must_not: [described_class.match("python")])
+      }.to raise_error(ArgumentError, /at least one must or should clause/)
+    end
+  end
+
+  describe ".boost" do
+    it "builds a boosting query" do
+      query = described_class.boost(described_class.match("ruby"), negative: described_class.match("rails"),
+                                    negative_boost: 0.5)
+      expect(query).to include(type: "boost", negative_boost: 0.5)
+    end
+  end
+end
@@ -105,6 +105,22 @@ RSpec.describe Lancelot::RankFusion do
         expect(fused[0][:rrf_score]).to be_within(0.0001).of(2.0 / 61)
       end
 
+      it "matches documents by row id when present" do
+        list1 = [
+          {text: "Same", _rowid: 1, _distance: 0.1},
+          {text: "Same", _rowid: 2, _distance: 0.2}
+        ]
+
+        list2 = [
+          {text: "Same", _rowid: 2, _score: 0.8}
+        ]
+
+        fused = described_class.reciprocal_rank_fusion([list1, list2])
+
+        expect(fused.map { |doc| doc[:_rowid] }).to eq([2, 1])
+        expect(fused[0][:rrf_score]).to be_within(0.0001).of(1.0 / 62 + 1.0 / 61)
+      end
+
       it "preserves all document fields in output" do
         doc = {id: 1, text: "Document", metadata: {author: "Test"}, score: 0.9}
         fused = described_class.reciprocal_rank_fusion([[doc]])